@@ -0,0 +1,37 @@
+use dioxus::prelude::*;
+
+fn main() {
+    blitz::launch(app);
+}
+
+fn app(cx: Scope) -> Element {
+    let styles = [
+        "solid", "dashed", "dotted", "double", "groove", "ridge", "inset", "outset",
+    ];
+    cx.render(rsx! {
+        div { flex_direction: "row", flex_wrap: "wrap", width: "100%", height: "100%",
+            styles.iter().map(|style| rsx! {
+                div {
+                    key: "{style}",
+                    width: "120px",
+                    height: "80px",
+                    margin: "10px",
+                    padding: "8px",
+                    border_width: "8px",
+                    border_style: "{style}",
+                    border_color: "rgb(40, 120, 200)",
+                    border_radius: "12px",
+                    "{style}"
+                }
+            })
+            div {
+                width: "120px",
+                height: "80px",
+                margin: "10px",
+                padding: "8px",
+                style: "border-top: 2px solid red; border-right: 8px dashed green; border-bottom: 14px double blue; border-left: 20px ridge orange;",
+                "mixed"
+            }
+        }
+    })
+}
@@ -0,0 +1,29 @@
+use blitz::use_element_rect;
+use dioxus::prelude::*;
+
+fn main() {
+    blitz::launch(app);
+}
+
+fn app(cx: Scope) -> Element {
+    cx.render(rsx! {
+        div { width: "100%", height: "100%",
+            chart {}
+        }
+    })
+}
+
+/// Draws one tick for every 80 pixels of width, so resizing the window changes the tick count.
+fn chart(cx: Scope) -> Element {
+    let rect = use_element_rect(&cx);
+    let width = rect.map_or(0.0, |rect| rect.content.width);
+    let ticks = (width / 80.0) as usize;
+
+    cx.render(rsx! {
+        div { flex_grow: "1", flex_direction: "row", justify_content: "space-between", background_color: "rgb(240, 240, 240)",
+            (0..=ticks).map(|tick| rsx! {
+                div { key: "{tick}", "{tick}" }
+            })
+        }
+    })
+}
@@ -0,0 +1,55 @@
+use dioxus::prelude::*;
+
+fn main() {
+    blitz::launch(app);
+}
+
+fn app(cx: Scope) -> Element {
+    let progress = 60;
+    cx.render(rsx! {
+        div { width: "100%", height: "100%",
+            div {
+                height: "60px",
+                padding: "16px",
+                color: "white",
+                background: "linear-gradient(to right, rgb(40, 60, 160), rgb(120, 40, 160) 70%, rgb(200, 60, 120))",
+                "header"
+            }
+            div { flex_direction: "row", flex_wrap: "wrap",
+                div {
+                    width: "160px",
+                    height: "100px",
+                    margin: "10px",
+                    background_image: "radial-gradient(circle at 30% 30%, white, rgb(40, 120, 200) 40%, rgb(10, 30, 80))",
+                }
+                div {
+                    width: "160px",
+                    height: "100px",
+                    margin: "10px",
+                    background_image: "repeating-linear-gradient(45deg, rgb(240, 200, 40) 0 10px, rgb(30, 30, 30) 10px 20px)",
+                }
+                div {
+                    width: "100px",
+                    height: "100px",
+                    margin: "10px",
+                    border_radius: "50%",
+                    background_image: "conic-gradient(red, yellow, lime, aqua, blue, magenta, red)",
+                }
+                div {
+                    width: "160px",
+                    height: "100px",
+                    margin: "10px",
+                    background: "linear-gradient(rgba(255, 255, 255, 0.8), transparent), repeating-radial-gradient(circle, rgb(200, 80, 40) 0 8px, rgb(240, 160, 80) 8px 16px)",
+                }
+            }
+            div { margin: "10px", height: "16px", border_radius: "8px", background_color: "rgb(220, 220, 220)",
+                div {
+                    width: "{progress}%",
+                    height: "100%",
+                    border_radius: "8px",
+                    background_image: "linear-gradient(to bottom, rgb(120, 220, 120), rgb(40, 160, 40))",
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,30 @@
+use dioxus::prelude::*;
+
+fn main() {
+    let mut app = blitz::HeadlessApp::new(app, 400, 300).unwrap();
+    app.save_png("headless.png").unwrap();
+}
+
+fn app(cx: Scope) -> Element {
+    cx.render(rsx! {
+        div {
+            width: "100%",
+            height: "100%",
+            background_color: "rgb(75%, 75%, 75%)",
+            justify_content: "center",
+            align_items: "center",
+
+            div {
+                width: "50%",
+                height: "50%",
+                background_color: "hsl(200, 100%, 50%)",
+                border_radius: "10px",
+                border_width: "4px",
+                border_style: "solid",
+                border_color: "black",
+
+                "Hello from a headless renderer!"
+            }
+        }
+    })
+}
@@ -0,0 +1,38 @@
+use dioxus::prelude::*;
+
+/// A 2x2 PNG with a red, a blue, a green and a yellow pixel.
+const PIXELS: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAYAAABytg0kAAAAG0lEQVR4nGO4Y2Pz36bizn8Gmy1R/z+c0PgPAFVECgu3/eu+AAAAAElFTkSuQmCC";
+
+fn main() {
+    blitz::launch(app);
+}
+
+fn app(cx: Scope) -> Element {
+    let status = use_state(&cx, || "loading".to_string());
+    let fits = ["fill", "contain", "cover", "none", "scale-down"];
+
+    cx.render(rsx! {
+        div { width: "100%", height: "100%", padding: "10px",
+            div { flex_direction: "row", flex_wrap: "wrap",
+                fits.iter().map(|fit| rsx! {
+                    div { key: "{fit}", margin: "10px",
+                        img {
+                            src: "{PIXELS}",
+                            width: "160px",
+                            height: "100px",
+                            background_color: "rgb(230, 230, 230)",
+                            style: "object-fit: {fit}; object-position: right bottom",
+                        }
+                        "{fit}"
+                    }
+                })
+            }
+            img {
+                src: "examples/missing.png",
+                onload: |_| status.set("loaded".to_string()),
+                onerror: |_| status.set("failed to load examples/missing.png".to_string()),
+            }
+            "{status}"
+        }
+    })
+}
@@ -1,15 +1,23 @@
+use blitz::{dpi::LogicalSize, Config};
 use dioxus::prelude::*;
 
 fn main() {
-    blitz::launch(app);
+    blitz::launch_cfg(
+        app,
+        Config::default()
+            .with_title("List")
+            .with_inner_size(LogicalSize::new(400.0, 600.0))
+            .with_min_inner_size(LogicalSize::new(200.0, 200.0)),
+    );
 }
 
 fn app(cx: Scope) -> Element {
     cx.render(rsx! {
+        style { ".asd {{ background-color: rgb(200, 200, 255); }} ul > li {{ border: 1px solid black; }}" }
         div { class: "asd", "Jon" }
-        div { display: "flex", flex_direction: "column", width: "100%", height: "100%",
+        div { display: "flex", flex_direction: "column", width: "100%", height: "100%", overflow: "auto",
             ul {
-                (1..8).map(|y|
+                (1..50).map(|y|
                     rsx! {
                         li {
                             key: "{y}",
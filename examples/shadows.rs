@@ -0,0 +1,43 @@
+use dioxus::prelude::*;
+
+fn main() {
+    blitz::launch(app);
+}
+
+fn app(cx: Scope) -> Element {
+    cx.render(rsx! {
+        div { flex_direction: "row", flex_wrap: "wrap", width: "100%", height: "100%", padding: "20px",
+            div {
+                width: "140px",
+                height: "90px",
+                margin: "20px",
+                background_color: "white",
+                box_shadow: "4px 4px 10px rgba(0, 0, 0, 0.5)",
+                "blurred"
+            }
+            div {
+                width: "140px",
+                height: "90px",
+                margin: "20px",
+                background_color: "white",
+                border_radius: "16px",
+                box_shadow: "0 0 0 6px rgb(40, 120, 200), 8px 8px 0 6px rgba(0, 0, 0, 0.3)",
+                "spread"
+            }
+            div {
+                width: "140px",
+                height: "90px",
+                margin: "20px",
+                background_color: "rgb(230, 230, 230)",
+                box_shadow: "inset 4px 4px 8px rgba(0, 0, 0, 0.5)",
+                "inset"
+            }
+            div {
+                margin: "20px",
+                font_size: "32px",
+                text_shadow: "2px 2px 4px rgba(0, 0, 0, 0.6)",
+                "text shadow"
+            }
+        }
+    })
+}
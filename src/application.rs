@@ -1,36 +1,83 @@
-use std::{
-    cell::RefCell,
-    rc::Rc,
-    sync::{Arc, Mutex, MutexGuard, Weak},
-};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
 
 use anymap::AnyMap;
-use dioxus::core::ElementId;
+use dioxus::core::{ElementId, Mutations};
 use dioxus::{
     core::{exports::futures_channel::mpsc::unbounded, SchedulerMsg, UserEvent},
     prelude::{Component, UnboundedSender, VirtualDom},
 };
 
 use futures_util::StreamExt;
-use piet_wgpu::{Piet, WgpuRenderer};
-use tao::{dpi::PhysicalSize, event_loop::EventLoopProxy, window::Window};
-
-use crate::{events::BlitzEventHandler, focus::FocusState, render::render, Dom, Redraw, TaoEvent};
-use dioxus_native_core::real_dom::RealDom;
-use taffy::{
-    prelude::{Number, Size},
-    Taffy,
+use fxhash::FxHashSet;
+use piet_wgpu::kurbo::Rect;
+use piet_wgpu::{Color, ImageFormat, InterpolationMode, Piet, RenderContext, WgpuRenderer};
+use tao::{
+    dpi::PhysicalSize,
+    event::{Event, WindowEvent},
+    window::{Window, WindowId},
 };
 
+use crate::{
+    events::BlitzEventHandler,
+    focus::FocusState,
+    image::{ImageLoader, LoadObserver, ResourceLoader},
+    layout::{compute_layout, LayoutContext},
+    query::RectObserver,
+    render::{Damage, RetainedFrame},
+    resize::ResizeObserver,
+    scroll::scroll_event,
+    stylesheet::{Cascade, ElementStates},
+    Config, Dom,
+};
+use dioxus_native_core::real_dom::RealDom;
+use taffy::prelude::{Number, Size};
+
+/// A dioxus app drawn into a window.
+///
+/// [`crate::launch_cfg`] creates one of these and runs the event loop for it. Apps that already
+/// own a tao event loop can create it for one of their windows and drive it themselves:
+///
+/// ```rust, ignore
+/// let proxy = event_loop.create_proxy();
+/// let mut blitz = ApplicationState::new(app, &window, move || {
+///     let _ = proxy.send_event(HostEvent::BlitzChanged);
+/// }, &Config::default());
+///
+/// event_loop.run(move |event, _, control_flow| {
+///     blitz.handle_event(&event);
+///     match event {
+///         Event::MainEventsCleared | Event::UserEvent(HostEvent::BlitzChanged) => {
+///             if blitz.needs_redraw() {
+///                 window.request_redraw();
+///             }
+///         }
+///         Event::RedrawRequested(id) if id == window.id() => blitz.render(),
+///         _ => (),
+///     }
+/// });
+/// ```
 pub struct ApplicationState {
     dom: DomManager,
+    window_id: WindowId,
     wgpu_renderer: WgpuRenderer,
+    /// The dom is painted into this frame, which is then copied to the window.
+    frame: RetainedFrame,
+    /// The color behind the dom, which lets the desktop show through transparent windows.
+    background: Color,
     event_handler: Arc<Mutex<BlitzEventHandler>>,
+    /// The element states after the last event, to find out if an event changed them.
+    element_states: ElementStates,
 }
 
 impl ApplicationState {
-    /// Create a new window state and spawn a vdom thread.
-    pub fn new(root: Component<()>, window: &Window, proxy: EventLoopProxy<Redraw>) -> Self {
+    /// Create a new window state and spawn a vdom thread. `redraw` is called from that thread
+    /// whenever the dom changed.
+    pub fn new(
+        root: Component<()>,
+        window: &Window,
+        redraw: impl Fn() + Send + 'static,
+        cfg: &Config,
+    ) -> Self {
         let inner_size = window.inner_size();
 
         let focus_state = Arc::new(Mutex::new(FocusState::default()));
@@ -42,9 +89,12 @@ impl ApplicationState {
         let dom = DomManager::spawn(
             inner_size,
             root,
-            proxy,
+            redraw,
             weak_event_handler,
             weak_focus_state,
+            cfg.default_stylesheet.clone(),
+            cfg.stylesheets.clone(),
+            cfg.resource_loaders.clone(),
         );
 
         let mut wgpu_renderer = WgpuRenderer::new(window).unwrap();
@@ -56,14 +106,48 @@ impl ApplicationState {
 
         ApplicationState {
             dom,
+            window_id: window.id(),
             wgpu_renderer,
+            frame: RetainedFrame::new().unwrap(),
+            background: if cfg.window.window.transparent {
+                Color::TRANSPARENT
+            } else {
+                Color::WHITE
+            },
             event_handler,
+            element_states: ElementStates::default(),
         }
     }
 
+    /// Repaint the parts of the window that changed.
     pub fn render(&mut self) {
-        let mut r = Piet::new(&mut self.wgpu_renderer);
-        self.dom.render(&mut r);
+        let size = self.dom.size();
+        let mut damage = self.dom.take_damage();
+        damage.extend(self.event_handler.lock().unwrap().take_damage());
+        let rdom = self.dom.rdom();
+        if let Err(err) = self.frame.paint(&rdom, size, &mut damage, self.background) {
+            println!("{}", err);
+            return;
+        }
+        drop(rdom);
+
+        // the swapchain image is undefined after it was presented, so the whole frame is copied
+        let mut piet = Piet::new(&mut self.wgpu_renderer);
+        let (width, height) = (size.width as usize, size.height as usize);
+        let window = Rect::new(0.0, 0.0, width as f64, height as f64);
+        piet.clear(window, Color::TRANSPARENT);
+        match piet.make_image(width, height, self.frame.pixels(), ImageFormat::RgbaPremul) {
+            Ok(image) => piet.draw_image(&image, window, InterpolationMode::NearestNeighbor),
+            Err(err) => println!("{}", err),
+        }
+        if let Err(err) = piet.finish() {
+            println!("{}", err);
+        }
+    }
+
+    /// If anything changed since the last call to [`ApplicationState::render`].
+    pub fn needs_redraw(&self) -> bool {
+        self.dom.needs_redraw() || self.event_handler.lock().unwrap().needs_redraw()
     }
 
     pub fn set_size(&mut self, size: PhysicalSize<u32>) {
@@ -77,29 +161,53 @@ impl ApplicationState {
         }
     }
 
-    pub fn clean(&self) -> DirtyNodes {
-        let dirty = self.dom.clean();
-        if self.event_handler.lock().unwrap().clean() {
-            DirtyNodes::All
-        } else {
-            dirty
+    /// Pass an event from the event loop to the app.
+    ///
+    /// Window events for other windows are ignored.
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        if let Event::WindowEvent {
+            window_id, event, ..
+        } = event
+        {
+            if *window_id != self.window_id {
+                return;
+            }
+            if let WindowEvent::Resized(size) = event {
+                self.set_size(*size);
+            }
         }
-    }
-
-    pub fn send_event(&mut self, event: &TaoEvent) {
         let size = self.dom.size();
         let size = Size {
             width: size.width,
             height: size.height,
         };
         let evts;
+        let restyle;
         {
             let rdom = &mut self.dom.rdom();
             let mut event_handler = self.event_handler.lock().unwrap();
             event_handler.register_event(event, rdom, &size);
+            let states = event_handler.element_states(rdom);
+            // the focus ring moved
+            if states.focused != self.element_states.focused {
+                for id in self
+                    .element_states
+                    .focused
+                    .into_iter()
+                    .chain(states.focused)
+                {
+                    event_handler.damage_node(rdom, id);
+                }
+            }
+            restyle = states != self.element_states;
+            self.element_states = states;
             evts = event_handler.drain_events();
         }
         self.dom.send_events(evts);
+        // pseudo-classes may match different elements now
+        if restyle {
+            self.dom.restyle();
+        }
     }
 }
 
@@ -107,8 +215,8 @@ impl ApplicationState {
 struct DomManager {
     rdom: Arc<Mutex<Dom>>,
     size: Arc<Mutex<PhysicalSize<u32>>>,
-    /// The node that need to be redrawn.
-    dirty: Arc<Mutex<Vec<ElementId>>>,
+    /// The area of the window that changed since the last frame.
+    damage: Arc<Mutex<Damage>>,
     force_redraw: bool,
     scheduler: UnboundedSender<SchedulerMsg>,
     redraw_sender: UnboundedSender<()>,
@@ -118,22 +226,26 @@ impl DomManager {
     fn spawn(
         size: PhysicalSize<u32>,
         root: Component<()>,
-        proxy: EventLoopProxy<Redraw>,
+        redraw: impl Fn() + Send + 'static,
         weak_event_handler: Weak<Mutex<BlitzEventHandler>>,
         weak_focus_state: Weak<Mutex<FocusState>>,
+        default_stylesheet: Option<String>,
+        stylesheets: Vec<String>,
+        resource_loaders: Vec<Arc<dyn ResourceLoader>>,
     ) -> Self {
         let rdom: Arc<Mutex<Dom>> = Arc::new(Mutex::new(RealDom::new()));
         let size = Arc::new(Mutex::new(size));
-        let dirty = Arc::new(Mutex::new(Vec::new()));
+        let damage = Arc::new(Mutex::new(Damage::default()));
 
         let weak_rdom = Arc::downgrade(&rdom);
         let weak_size = Arc::downgrade(&size);
-        let weak_dirty = Arc::downgrade(&dirty);
+        let weak_damage = Arc::downgrade(&damage);
 
         let channel_sender = Arc::new(Mutex::new(None));
         let channel_sender_weak = Arc::downgrade(&channel_sender);
 
         let (redraw_sender, mut redraw_receiver) = unbounded::<()>();
+        let image_sender = redraw_sender.clone();
 
         // Spawn a thread to run the virtual dom and update the real dom.
         std::thread::spawn(move || {
@@ -142,8 +254,19 @@ impl DomManager {
                 .build()
                 .unwrap()
                 .block_on(async {
-                    let stretch = Rc::new(RefCell::new(Taffy::new()));
+                    // wake up this thread when an image finished loading
+                    let images = ImageLoader::new(resource_loaders, move || {
+                        let _ = image_sender.unbounded_send(());
+                    });
+                    let layout_ctx = LayoutContext::new(
+                        Cascade::new(default_stylesheet.as_deref(), &stylesheets),
+                        images,
+                    )
+                    .unwrap();
                     let mut vdom = VirtualDom::new(root);
+                    let rects = RectObserver::provide(&vdom);
+                    let mut resize = ResizeObserver::default();
+                    let mut loads = LoadObserver::default();
                     channel_sender_weak
                         .upgrade()
                         .unwrap()
@@ -154,12 +277,13 @@ impl DomManager {
                     let mut last_size = Size::undefined();
                     if let Some(strong) = weak_rdom.upgrade() {
                         if let Ok(mut rdom) = strong.lock() {
-                            // update the real dom's nodes
-                            let to_update = rdom.apply_mutations(vec![mutations]);
-                            let mut ctx = AnyMap::new();
-                            ctx.insert(stretch.clone());
-                            // update the style and layout
-                            let to_rerender = rdom.update_state(&vdom, to_update, ctx);
+                            let to_rerender = update_state(
+                                &mut rdom,
+                                &vdom,
+                                vec![mutations],
+                                &layout_ctx,
+                                &ElementStates::default(),
+                            );
                             if let Some(strong) = weak_size.upgrade() {
                                 let size = strong.lock().unwrap();
 
@@ -170,26 +294,20 @@ impl DomManager {
 
                                 last_size = size;
 
-                                stretch
-                                    .borrow_mut()
-                                    .compute_layout(
-                                        rdom[ElementId(rdom.root_id())].state.layout.node.unwrap(),
-                                        size,
-                                    )
-                                    .unwrap();
-                                rdom.traverse_depth_first_mut(|n| {
-                                    if let Some(node) = n.state.layout.node {
-                                        n.state.layout.layout =
-                                            Some(*stretch.borrow().layout(node).unwrap());
-                                    }
-                                });
-                                weak_dirty
-                                    .upgrade()
-                                    .unwrap()
-                                    .lock()
-                                    .unwrap()
-                                    .extend(to_rerender.iter());
-                                proxy.send_event(Redraw).unwrap();
+                                let changes = compute_layout(&mut rdom, &layout_ctx, size);
+                                rects.update(&rdom, &vdom);
+                                for event in
+                                    resize.update(&rdom).into_iter().chain(loads.update(&rdom))
+                                {
+                                    vdom.handle_message(SchedulerMsg::Event(event));
+                                }
+                                let damage = weak_damage.upgrade().unwrap();
+                                let mut damage = damage.lock().unwrap();
+                                damage.extend(changes.damage);
+                                for id in to_rerender {
+                                    damage.add_node(&rdom, id);
+                                }
+                                redraw();
                             }
                         }
                     }
@@ -203,6 +321,8 @@ impl DomManager {
                         if let Some(strong) = weak_rdom.upgrade() {
                             if let Ok(mut rdom) = strong.lock() {
                                 let mutations = vdom.work_with_deadline(|| false);
+                                let states;
+                                let moved;
                                 if let Some(strong) = weak_focus_state.upgrade() {
                                     if let Ok(mut focus_state) = strong.lock() {
                                         if let Some(strong) = weak_event_handler.upgrade() {
@@ -211,6 +331,10 @@ impl DomManager {
                                                     event_handler.prune(m, &rdom);
                                                     focus_state.prune(m, &rdom);
                                                 }
+                                                // reading the element states locks the focus state again
+                                                drop(focus_state);
+                                                states = event_handler.element_states(&rdom);
+                                                moved = event_handler.take_moved();
                                             } else {
                                                 break;
                                             }
@@ -223,14 +347,15 @@ impl DomManager {
                                 } else {
                                     break;
                                 }
-                                // update the real dom's nodes
-                                let to_update = rdom.apply_mutations(mutations);
-
-                                let mut ctx = AnyMap::new();
-                                ctx.insert(stretch.clone());
-
-                                // update the style and layout
-                                let to_rerender = rdom.update_state(&vdom, to_update, ctx);
+                                // the removed nodes are gone after the update
+                                weak_damage
+                                    .upgrade()
+                                    .unwrap()
+                                    .lock()
+                                    .unwrap()
+                                    .add_removed(&rdom, &mutations);
+                                let to_rerender =
+                                    update_state(&mut rdom, &vdom, mutations, &layout_ctx, &states);
 
                                 if let Some(strong) = weak_size.upgrade() {
                                     let size = *strong.lock().unwrap();
@@ -239,33 +364,41 @@ impl DomManager {
                                         width: Number::Defined(size.width as f32),
                                         height: Number::Defined(size.height as f32),
                                     };
-                                    if !to_rerender.is_empty() || last_size != size {
+                                    let relayout = !to_rerender.is_empty() || last_size != size;
+                                    if relayout {
                                         last_size = size;
-                                        stretch
-                                            .borrow_mut()
-                                            .compute_layout(
-                                                rdom[ElementId(rdom.root_id())]
-                                                    .state
-                                                    .layout
-                                                    .node
-                                                    .unwrap(),
-                                                size,
-                                            )
-                                            .unwrap();
-                                        rdom.traverse_depth_first_mut(|n| {
-                                            if let Some(node) = n.state.layout.node {
-                                                n.state.layout.layout =
-                                                    Some(*stretch.borrow().layout(node).unwrap());
+                                        let changes = compute_layout(&mut rdom, &layout_ctx, size);
+                                        // elements moved, so the mouse may be over others now
+                                        match weak_event_handler.upgrade() {
+                                            Some(strong) => {
+                                                strong.lock().unwrap().invalidate_layout()
                                             }
-                                        });
-                                        weak_dirty
-                                            .upgrade()
-                                            .unwrap()
-                                            .lock()
-                                            .unwrap()
-                                            .extend(to_rerender.into_iter());
-
-                                        proxy.send_event(Redraw).unwrap();
+                                            None => break,
+                                        }
+                                        // content that shrank can clamp scroll offsets
+                                        let scrolled =
+                                            changes.scrolled.into_iter().map(scroll_event);
+                                        for event in resize
+                                            .update(&rdom)
+                                            .into_iter()
+                                            .chain(loads.update(&rdom))
+                                            .chain(scrolled)
+                                        {
+                                            vdom.handle_message(SchedulerMsg::Event(event));
+                                        }
+                                        let damage = weak_damage.upgrade().unwrap();
+                                        let mut damage = damage.lock().unwrap();
+                                        damage.extend(changes.damage);
+                                        for id in to_rerender {
+                                            damage.add_node(&rdom, id);
+                                        }
+                                        drop(damage);
+
+                                        redraw();
+                                    }
+                                    // scrolling moves elements without a new layout
+                                    if relayout || moved {
+                                        rects.update(&rdom, &vdom);
                                     }
                                 } else {
                                     break;
@@ -288,19 +421,21 @@ impl DomManager {
         Self {
             rdom,
             size,
-            dirty,
+            damage,
             scheduler: sender_lock.take().unwrap(),
             redraw_sender,
             force_redraw: false,
         }
     }
 
-    fn clean(&self) -> DirtyNodes {
-        if self.force_redraw {
-            DirtyNodes::All
-        } else {
-            DirtyNodes::Some(std::mem::take(&mut *self.dirty.lock().unwrap()))
-        }
+    /// Take the area that changed since the last frame.
+    fn take_damage(&mut self) -> Damage {
+        self.force_redraw = false;
+        std::mem::take(&mut *self.damage.lock().unwrap())
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.force_redraw || !self.damage.lock().unwrap().is_empty()
     }
 
     fn rdom(&self) -> MutexGuard<Dom> {
@@ -316,13 +451,14 @@ impl DomManager {
         *self.size.lock().unwrap()
     }
 
-    fn force_redraw(&mut self) {
-        self.force_redraw = true;
+    /// Wake up the vdom thread to match the stylesheets against the dom again.
+    fn restyle(&self) {
         self.redraw_sender.unbounded_send(()).unwrap();
     }
 
-    fn render(&self, renderer: &mut Piet) {
-        render(&self.rdom(), renderer, *self.size.lock().unwrap());
+    fn force_redraw(&mut self) {
+        self.force_redraw = true;
+        self.redraw_sender.unbounded_send(()).unwrap();
     }
 
     fn send_events(&self, events: Vec<UserEvent>) {
@@ -334,16 +470,26 @@ impl DomManager {
     }
 }
 
-pub enum DirtyNodes {
-    All,
-    Some(Vec<ElementId>),
-}
-
-impl DirtyNodes {
-    pub fn is_empty(&self) -> bool {
-        match self {
-            DirtyNodes::All => false,
-            DirtyNodes::Some(v) => v.is_empty(),
-        }
-    }
+/// Apply mutations to the real dom and return the nodes that need to be rerendered.
+pub(crate) fn update_state(
+    rdom: &mut Dom,
+    vdom: &VirtualDom,
+    mutations: Vec<Mutations>,
+    layout_ctx: &LayoutContext,
+    states: &ElementStates,
+) -> FxHashSet<ElementId> {
+    // update the real dom's nodes
+    let mut to_update = rdom.apply_mutations(mutations);
+    // restyle the nodes the stylesheets now apply differently to
+    let restyled = layout_ctx.cascade.update(rdom, vdom, &to_update, states);
+    to_update.extend(restyled);
+    // lay out the images that finished loading with their size
+    to_update.extend(layout_ctx.images.update(rdom));
+
+    let mut ctx = AnyMap::new();
+    ctx.insert(layout_ctx.clone());
+    ctx.insert(layout_ctx.cascade.clone());
+
+    // update the style and layout
+    rdom.update_state(vdom, to_update, ctx)
 }
@@ -0,0 +1,344 @@
+use parcel_css::properties::border::LineStyle;
+use parcel_css::values::length::LengthPercentage;
+use parcel_css::values::size::Size2D;
+use piet_wgpu::kurbo::{BezPath, Circle, Insets, Point, Rect, Shape, Vec2};
+use piet_wgpu::{Color, RenderContext};
+use taffy::prelude::Size;
+
+use crate::util::{resolve_color, Axis, Resolve};
+use crate::DomNode;
+
+/// How far the control points of a cubic bezier are along the tangents of a quarter ellipse.
+const KAPPA: f64 = 0.552_284_75;
+
+/// How much darker the shaded sides of `inset`, `outset`, `groove` and `ridge` borders are.
+const SHADE: f64 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Side {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+impl Side {
+    const ALL: [Side; 4] = [Side::Top, Side::Right, Side::Bottom, Side::Left];
+
+    /// The sides the light falls on in the 3D border styles.
+    fn lit(self) -> bool {
+        matches!(self, Side::Top | Side::Left)
+    }
+}
+
+/// The outline of an element's border box with its corners rounded. The padding and content
+/// boxes are inset from it.
+pub(crate) struct BoxShape {
+    outer: Rect,
+    /// The horizontal and vertical radii of the top left, top right, bottom right and bottom
+    /// left corners.
+    radii: [Vec2; 4],
+}
+
+impl BoxShape {
+    pub(crate) fn new(node: &DomNode, viewport_size: &Size<u32>) -> Self {
+        let outer = node.state.layout.boxes.border;
+        let size = node.state.layout.layout.unwrap().size;
+        let radius = &node.state.style.border.radius;
+        let resolve = |corner: &Size2D<LengthPercentage>| {
+            Vec2::new(
+                corner.0.resolve(Axis::X, &size, viewport_size),
+                corner.1.resolve(Axis::Y, &size, viewport_size),
+            )
+        };
+        let mut radii = [
+            resolve(&radius.top_left),
+            resolve(&radius.top_right),
+            resolve(&radius.bottom_right),
+            resolve(&radius.bottom_left),
+        ];
+        // corners that would overlap are all shrunk by the same factor
+        let [tl, tr, br, bl] = radii;
+        let scale = [
+            outer.width() / (tl.x + tr.x),
+            outer.width() / (bl.x + br.x),
+            outer.height() / (tl.y + bl.y),
+            outer.height() / (tr.y + br.y),
+        ]
+        .into_iter()
+        .fold(1.0, f64::min);
+        for radius in &mut radii {
+            *radius *= scale;
+        }
+        Self { outer, radii }
+    }
+
+    pub(crate) fn border_box(&self) -> BezPath {
+        self.inset(Insets::uniform(0.0))
+    }
+
+    /// The outline inset from the border box, with the corners rounded less by the insets of
+    /// the sides next to them. Negative insets grow the box.
+    pub(crate) fn inset(&self, insets: Insets) -> BezPath {
+        let x0 = self.outer.x0 + insets.x0;
+        let y0 = self.outer.y0 + insets.y0;
+        let x1 = self.outer.x1 - insets.x1;
+        let y1 = self.outer.y1 - insets.y1;
+        let shrink = |radius: Vec2, x: f64, y: f64| {
+            if radius.x > 0.0 && radius.y > 0.0 {
+                Vec2::new((radius.x - x).max(0.0), (radius.y - y).max(0.0))
+            } else {
+                Vec2::ZERO
+            }
+        };
+        let tl = shrink(self.radii[0], insets.x0, insets.y0);
+        let tr = shrink(self.radii[1], insets.x1, insets.y0);
+        let br = shrink(self.radii[2], insets.x1, insets.y1);
+        let bl = shrink(self.radii[3], insets.x0, insets.y1);
+
+        // each corner is a quarter of an ellipse, drawn as a cubic bezier
+        let k = 1.0 - KAPPA;
+        let mut path = BezPath::new();
+        path.move_to((x0 + tl.x, y0));
+        path.line_to((x1 - tr.x, y0));
+        path.curve_to((x1 - tr.x * k, y0), (x1, y0 + tr.y * k), (x1, y0 + tr.y));
+        path.line_to((x1, y1 - br.y));
+        path.curve_to((x1, y1 - br.y * k), (x1 - br.x * k, y1), (x1 - br.x, y1));
+        path.line_to((x0 + bl.x, y1));
+        path.curve_to((x0 + bl.x * k, y1), (x0, y1 - bl.y * k), (x0, y1 - bl.y));
+        path.line_to((x0, y0 + tl.y));
+        path.curve_to((x0, y0 + tl.y * k), (x0 + tl.x * k, y0), (x0 + tl.x, y0));
+        path.close_path();
+        path
+    }
+}
+
+/// The insets that turn one box of an element into a box inside it.
+pub(crate) fn insets_between(outer: Rect, inner: Rect) -> Insets {
+    Insets::new(
+        inner.x0 - outer.x0,
+        inner.y0 - outer.y0,
+        outer.x1 - inner.x1,
+        outer.y1 - inner.y1,
+    )
+}
+
+/// The area between the border box and the padding box of an element.
+struct BorderGeometry {
+    shape: BoxShape,
+    widths: Insets,
+}
+
+impl BorderGeometry {
+    fn width(&self, side: Side) -> f64 {
+        match side {
+            Side::Top => self.widths.y0,
+            Side::Right => self.widths.x1,
+            Side::Bottom => self.widths.y1,
+            Side::Left => self.widths.x0,
+        }
+    }
+
+    /// The edge a fraction of the way from the outside of the border to the inside.
+    fn shape_at(&self, fraction: f64) -> BezPath {
+        let w = &self.widths;
+        self.shape.inset(Insets::new(
+            w.x0 * fraction,
+            w.y0 * fraction,
+            w.x1 * fraction,
+            w.y1 * fraction,
+        ))
+    }
+
+    /// The part of the border between two fractions of its width. Fill it with the even-odd rule.
+    fn band(&self, from: f64, to: f64) -> BezPath {
+        let mut path = self.shape_at(from);
+        path.extend(self.shape_at(to));
+        path
+    }
+
+    /// The part of the border box that belongs to a side. Neighboring sides meet along the line
+    /// from the outer corner to the inner corner, so the join follows the ratio of their widths.
+    fn wedge(&self, side: Side) -> BezPath {
+        let o = self.shape.outer;
+        let i = o - self.widths;
+        let corners = match side {
+            Side::Top => [(o.x0, o.y0), (o.x1, o.y0), (i.x1, i.y0), (i.x0, i.y0)],
+            Side::Right => [(o.x1, o.y0), (o.x1, o.y1), (i.x1, i.y1), (i.x1, i.y0)],
+            Side::Bottom => [(o.x1, o.y1), (o.x0, o.y1), (i.x0, i.y1), (i.x1, i.y1)],
+            Side::Left => [(o.x0, o.y1), (o.x0, o.y0), (i.x0, i.y0), (i.x0, i.y1)],
+        };
+        let mut path = BezPath::new();
+        path.move_to(corners[0]);
+        for corner in &corners[1..] {
+            path.line_to(*corner);
+        }
+        path.close_path();
+        path
+    }
+}
+
+/// Paint each side of an element's border with its own width, color and style.
+pub(crate) fn render_border(
+    node: &DomNode,
+    piet: &mut impl RenderContext,
+    viewport_size: &Size<u32>,
+) {
+    let boxes = &node.state.layout.boxes;
+    let geometry = BorderGeometry {
+        shape: BoxShape::new(node, viewport_size),
+        widths: insets_between(boxes.border, boxes.padding),
+    };
+    if Side::ALL.iter().all(|side| geometry.width(*side) <= 0.0) {
+        return;
+    }
+
+    let style = &node.state.style;
+    let border = &style.border;
+    let current_color = &style.color.0;
+    let sides = Side::ALL.map(|side| {
+        let (style, color) = match side {
+            Side::Top => (border.styles.top, &border.colors.top),
+            Side::Right => (border.styles.right, &border.colors.right),
+            Side::Bottom => (border.styles.bottom, &border.colors.bottom),
+            Side::Left => (border.styles.left, &border.colors.left),
+        };
+        (side, style, resolve_color(color, current_color))
+    });
+
+    // a border that is the same all around is painted in one go so the corners have no seams
+    let (_, first_style, first_color) = &sides[0];
+    if *first_style == LineStyle::Solid
+        && sides.iter().all(|(side, style, color)| {
+            style == first_style && color == first_color && geometry.width(*side) > 0.0
+        })
+    {
+        fill_band(&geometry, 0.0, 1.0, first_color, piet);
+        return;
+    }
+
+    for (side, style, color) in sides {
+        if geometry.width(side) > 0.0 {
+            piet.save().unwrap();
+            piet.clip(geometry.wedge(side));
+            render_side(&geometry, side, style, color, piet);
+            piet.restore().unwrap();
+        }
+    }
+}
+
+/// Paint one side of a border. The painting is already clipped to the side's wedge.
+fn render_side(
+    geometry: &BorderGeometry,
+    side: Side,
+    style: LineStyle,
+    color: Color,
+    piet: &mut impl RenderContext,
+) {
+    let width = geometry.width(side);
+    let shaded = shade(&color);
+    match style {
+        LineStyle::None | LineStyle::Hidden => {}
+        // there is no room for a gap between two lines in thin borders
+        LineStyle::Double if width >= 3.0 => {
+            fill_band(geometry, 0.0, 1.0 / 3.0, &color, piet);
+            fill_band(geometry, 2.0 / 3.0, 1.0, &color, piet);
+        }
+        LineStyle::Solid | LineStyle::Double => fill_band(geometry, 0.0, 1.0, &color, piet),
+        LineStyle::Inset | LineStyle::Outset => {
+            let lit = side.lit() == (style == LineStyle::Outset);
+            let color = if lit { &color } else { &shaded };
+            fill_band(geometry, 0.0, 1.0, color, piet);
+        }
+        LineStyle::Groove | LineStyle::Ridge => {
+            // the outer half of a groove is shaded like an inset border and the inner half like an
+            // outset one, and a ridge is the other way around
+            let outer_lit = side.lit() == (style == LineStyle::Ridge);
+            let (outer, inner) = if outer_lit {
+                (&color, &shaded)
+            } else {
+                (&shaded, &color)
+            };
+            fill_band(geometry, 0.0, 0.5, outer, piet);
+            fill_band(geometry, 0.5, 1.0, inner, piet);
+        }
+        LineStyle::Dashed | LineStyle::Dotted => {
+            render_dashes(geometry, side, style == LineStyle::Dotted, color, piet)
+        }
+    }
+}
+
+fn fill_band(
+    geometry: &BorderGeometry,
+    from: f64,
+    to: f64,
+    color: &Color,
+    piet: &mut impl RenderContext,
+) {
+    let brush = piet.solid_brush(color.clone());
+    piet.fill_even_odd(geometry.band(from, to), &brush);
+}
+
+/// Paint dashes or dots along a side, spaced so the side starts and ends with one.
+fn render_dashes(
+    geometry: &BorderGeometry,
+    side: Side,
+    dotted: bool,
+    color: Color,
+    piet: &mut impl RenderContext,
+) {
+    let width = geometry.width(side);
+    let o = geometry.shape.outer;
+    let half = width / 2.0;
+    // the line through the middle of the side
+    let (start, end) = match side {
+        Side::Top => (Point::new(o.x0, o.y0 + half), Point::new(o.x1, o.y0 + half)),
+        Side::Right => (Point::new(o.x1 - half, o.y0), Point::new(o.x1 - half, o.y1)),
+        Side::Bottom => (Point::new(o.x0, o.y1 - half), Point::new(o.x1, o.y1 - half)),
+        Side::Left => (Point::new(o.x0 + half, o.y0), Point::new(o.x0 + half, o.y1)),
+    };
+    let length = (end - start).hypot();
+    if length <= 0.0 {
+        return;
+    }
+    let direction = (end - start) / length;
+    let (dash, gap) = if dotted {
+        (width, width)
+    } else {
+        (width * 2.0, width)
+    };
+    let count = ((length + gap) / (dash + gap)).floor().max(1.0) as usize;
+    let step = if count > 1 {
+        (length - dash) / (count - 1) as f64
+    } else {
+        0.0
+    };
+
+    let mut path = BezPath::new();
+    for i in 0..count {
+        let offset = if count > 1 {
+            dash / 2.0 + step * i as f64
+        } else {
+            length / 2.0
+        };
+        let center = start + direction * offset;
+        if dotted {
+            path.extend(Circle::new(center, half).path_elements(0.1));
+        } else {
+            let size = match side {
+                Side::Top | Side::Bottom => (dash, width),
+                Side::Left | Side::Right => (width, dash),
+            };
+            path.extend(Rect::from_center_size(center, size).path_elements(0.1));
+        }
+    }
+    // dashes running into a rounded corner are cut off by it
+    piet.clip(geometry.shape_at(0.0));
+    let brush = piet.solid_brush(color);
+    piet.fill(path, &brush);
+}
+
+fn shade(color: &Color) -> Color {
+    let (r, g, b, a) = color.as_rgba();
+    Color::rgba(r * SHADE, g * SHADE, b * SHADE, a)
+}
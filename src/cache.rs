@@ -0,0 +1,147 @@
+use std::sync::{Arc, Weak};
+
+use fxhash::FxHashMap;
+use piet_wgpu::kurbo::{Point, Rect, Vec2};
+use piet_wgpu::{Color, ImageFormat, InterpolationMode, RenderContext};
+
+use crate::image::DecodedImage;
+use crate::style::Font;
+use crate::text::TextContext;
+
+/// A line of text rasterized by the text context and uploaded to a render context.
+pub(crate) struct CachedText<I> {
+    font: Font,
+    color: u32,
+    blur: f64,
+    image: Option<I>,
+    bounds: Rect,
+    used: bool,
+}
+
+impl<I> CachedText<I> {
+    /// Draw the text with the top left corner of its layout at `pos`, rounded to whole pixels.
+    pub(crate) fn draw<P: RenderContext<Image = I>>(&self, piet: &mut P, pos: Point) {
+        if let Some(image) = &self.image {
+            let offset = Vec2::new(pos.x.round(), pos.y.round());
+            piet.draw_image(
+                image,
+                self.bounds + offset,
+                InterpolationMode::NearestNeighbor,
+            );
+        }
+    }
+}
+
+/// A decoded image uploaded to a render context.
+struct CachedImage<I> {
+    /// Keeps the address of the decoded image from being reused while it is a key.
+    source: Weak<DecodedImage>,
+    image: I,
+}
+
+/// Resources uploaded to a render context, kept between frames so they are only created once.
+pub(crate) struct RenderCache<I> {
+    text_ctx: TextContext,
+    /// The rasterized lines of text by their content.
+    text: FxHashMap<String, Vec<CachedText<I>>>,
+    /// The uploaded images by the address of their decoded image.
+    images: FxHashMap<usize, CachedImage<I>>,
+}
+
+impl<I> RenderCache<I> {
+    pub(crate) fn new() -> Result<Self, piet_common::Error> {
+        Ok(Self {
+            text_ctx: TextContext::new()?,
+            text: FxHashMap::default(),
+            images: FxHashMap::default(),
+        })
+    }
+
+    /// Get a line of text ready to be drawn, rasterizing it if it wasn't drawn in the last frame.
+    pub(crate) fn text<P: RenderContext<Image = I>>(
+        &mut self,
+        piet: &mut P,
+        text: &str,
+        font: &Font,
+        color: &Color,
+        blur: f64,
+    ) -> &CachedText<I> {
+        let key = color.as_rgba_u32();
+        if !self.text.contains_key(text) {
+            self.text.insert(text.to_string(), Vec::new());
+        }
+        let entries = self.text.get_mut(text).unwrap();
+        let index = match entries
+            .iter()
+            .position(|e| e.font == *font && e.color == key && e.blur == blur)
+        {
+            Some(index) => index,
+            None => {
+                let bitmap = self.text_ctx.rasterize(text, font, color.clone(), blur);
+                let image = (!bitmap.pixels.is_empty())
+                    .then(|| {
+                        piet.make_image(
+                            bitmap.width,
+                            bitmap.height,
+                            &bitmap.pixels,
+                            ImageFormat::RgbaPremul,
+                        )
+                        .ok()
+                    })
+                    .flatten();
+                entries.push(CachedText {
+                    font: font.clone(),
+                    color: key,
+                    blur,
+                    image,
+                    bounds: bitmap.bounds,
+                    used: false,
+                });
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[index];
+        entry.used = true;
+        entry
+    }
+
+    /// Get a decoded image ready to be drawn, uploading it the first time it is drawn.
+    ///
+    /// Returns `None` if the render context can't create the image.
+    pub(crate) fn image<P: RenderContext<Image = I>>(
+        &mut self,
+        piet: &mut P,
+        image: &Arc<DecodedImage>,
+    ) -> Option<&I> {
+        let key = Arc::as_ptr(image) as usize;
+        if !self.images.contains_key(&key) {
+            let uploaded = piet
+                .make_image(
+                    image.width as usize,
+                    image.height as usize,
+                    &image.pixels,
+                    ImageFormat::RgbaSeparate,
+                )
+                .ok()?;
+            self.images.insert(
+                key,
+                CachedImage {
+                    source: Arc::downgrade(image),
+                    image: uploaded,
+                },
+            );
+        }
+        self.images.get(&key).map(|cached| &cached.image)
+    }
+
+    /// Drop the text that wasn't used since the last call and the images nothing shows any
+    /// more. Call this after each frame.
+    pub(crate) fn end_frame(&mut self) {
+        self.text.retain(|_, entries| {
+            entries.retain_mut(|e| std::mem::take(&mut e.used));
+            !entries.is_empty()
+        });
+        self.images
+            .retain(|_, cached| cached.source.strong_count() > 0);
+    }
+}
@@ -1,27 +1,40 @@
-use piet_wgpu::kurbo::Point;
+use piet_wgpu::kurbo::{Point, Vec2};
 use std::{
     str::FromStr,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 use taffy::prelude::Size;
-use tao::event::MouseButton;
+use tao::event::{MouseButton, MouseScrollDelta};
 
 use dioxus::{
     core::{ElementId, EventPriority, Mutations, UserEvent},
-    events::{KeyboardData, MouseData},
+    events::{KeyboardData, MouseData, WheelData},
     prelude::dioxus_elements::{
         geometry::{
-            euclid::Point2D, ClientPoint, Coordinates, ElementPoint, PagePoint, ScreenPoint,
+            euclid::Point2D, ClientPoint, Coordinates, ElementPoint, LinesVector, PagePoint,
+            PixelsVector, ScreenPoint, WheelDelta,
         },
         input_data::{self, keyboard_types::Modifiers, MouseButtonSet},
     },
 };
+use dioxus_native_core::node_ref::{AttributeMask, NodeMask, NodeView};
 use dioxus_native_core::real_dom::NodeType;
+use dioxus_native_core::state::NodeDepState;
 
 use tao::keyboard::Key;
 
-use crate::{focus::FocusState, mouse::get_hovered, node::PreventDefault, Dom, TaoEvent};
+use crate::{
+    focus::FocusState,
+    is_document_node,
+    layout::LayoutChanges,
+    mouse::HitTestIndex,
+    node::PreventDefault,
+    render::Damage,
+    scroll::{scroll_by, scroll_event, PIXELS_PER_LINE},
+    stylesheet::ElementStates,
+    Dom, DomNode,
+};
 
 const DBL_CLICK_TIME: Duration = Duration::from_millis(500);
 
@@ -31,7 +44,10 @@ struct CursorState {
     last_click: Option<Instant>,
     last_pressed_element: Option<ElementId>,
     last_clicked_element: Option<ElementId>,
+    /// The deepest element under the cursor.
     hovered: Option<ElementId>,
+    /// The hovered element followed by all of its ancestors.
+    hovered_chain: Vec<ElementId>,
 }
 
 impl Default for CursorState {
@@ -48,6 +64,7 @@ impl Default for CursorState {
             last_pressed_element: Default::default(),
             last_clicked_element: Default::default(),
             hovered: Default::default(),
+            hovered_chain: Default::default(),
         }
     }
 }
@@ -57,12 +74,61 @@ struct EventState {
     modifier_state: Modifiers,
     cursor_state: CursorState,
     focus_state: Arc<Mutex<FocusState>>,
+    /// If the focus was last moved with the keyboard.
+    keyboard_focus: bool,
 }
 
 #[derive(Default)]
 pub struct BlitzEventHandler {
     state: EventState,
     queued_events: Vec<UserEvent>,
+    /// The area the events changed since the last frame.
+    damage: Damage,
+    /// If a scroll container moved since the vdom last read where the elements are.
+    moved: bool,
+    hit_test: HitTestIndex,
+}
+
+/// An element followed by all of its ancestors.
+fn element_chain(rdom: &Dom, element: Option<ElementId>) -> Vec<ElementId> {
+    let mut chain = Vec::new();
+    let mut current = element;
+    while let Some(id) = current {
+        if !is_document_node(id) {
+            break;
+        }
+        chain.push(id);
+        current = rdom[id].parent;
+    }
+    chain
+}
+
+/// How far the scroll containers around an element moved it in the window.
+fn scroll_offset(rdom: &Dom, element: Option<ElementId>) -> Vec2 {
+    element_chain(rdom, element)
+        .iter()
+        .skip(1)
+        .fold(Vec2::ZERO, |offset, id| offset + rdom[*id].state.scroll)
+}
+
+/// The events an element has listeners for.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct Listeners(pub Vec<&'static str>);
+
+impl NodeDepState<()> for Listeners {
+    type Ctx = ();
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(&[])).with_listeners();
+
+    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), _: &Self::Ctx) -> bool {
+        let new = Listeners(node.listeners().iter().map(|l| l.event).collect());
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 impl BlitzEventHandler {
@@ -76,9 +142,9 @@ impl BlitzEventHandler {
         }
     }
 
-    pub(crate) fn register_event(
+    pub(crate) fn register_event<T>(
         &mut self,
-        event: &TaoEvent,
+        event: &tao::event::Event<T>,
         rdom: &mut Dom,
         viewport_size: &Size<u32>,
     ) {
@@ -143,6 +209,7 @@ impl BlitzEventHandler {
                                 });
                             }
                             if let Key::Tab = event.logical_key {
+                                self.state.keyboard_focus = true;
                                 self.state.focus_state.lock().unwrap().progress(
                                     rdom,
                                     !self.state.modifier_state.contains(Modifiers::SHIFT),
@@ -186,78 +253,84 @@ impl BlitzEventHandler {
                         ..
                     } => {
                         let pos = Point::new(position.x as f64, position.y as f64);
-                        let hovered = get_hovered(rdom, viewport_size, pos);
-                        let (mouse_x, mouse_y) = (pos.x as i32, pos.y as i32);
-                        let screen_point = ScreenPoint::new(mouse_x as f64, mouse_y as f64);
-                        let client_point = ClientPoint::new(mouse_x as f64, mouse_y as f64);
-                        let page_point = PagePoint::new(mouse_x as f64, mouse_y as f64);
-                        // the position of the element is subtracted later
-                        let element_point = ElementPoint::new(mouse_x as f64, mouse_y as f64);
-                        let position =
+                        let hovered = self.hit_test.hit(rdom, viewport_size, pos);
+                        let (mouse_x, mouse_y) = (pos.x as i32 as f64, pos.y as i32 as f64);
+                        let screen_point = ScreenPoint::new(mouse_x, mouse_y);
+                        let client_point = ClientPoint::new(mouse_x, mouse_y);
+                        // the page moves under the window when the containers around it scroll
+                        let scroll = scroll_offset(rdom, hovered);
+                        let page_point = PagePoint::new(mouse_x + scroll.x, mouse_y + scroll.y);
+                        // the position of the element is subtracted for each element the event is sent to
+                        let element_point = ElementPoint::new(mouse_x, mouse_y);
+                        self.state.cursor_state.position =
                             Coordinates::new(screen_point, client_point, element_point, page_point);
 
-                        let data = MouseData::new(
-                            Coordinates::new(screen_point, client_point, element_point, page_point),
-                            None,
-                            self.state.cursor_state.buttons,
-                            self.state.modifier_state,
-                        );
-                        match (hovered, self.state.cursor_state.hovered) {
-                            (Some(hovered), Some(old_hovered)) => {
-                                if hovered != old_hovered {
-                                    self.queued_events.push(UserEvent {
-                                        scope_id: None,
-                                        priority: EventPriority::Medium,
-                                        element: Some(hovered),
-                                        name: "mouseenter",
-                                        data: Arc::new(data.clone()),
-                                        bubbles: true,
-                                    });
-                                    self.queued_events.push(UserEvent {
-                                        scope_id: None,
-                                        priority: EventPriority::Medium,
-                                        element: Some(old_hovered),
-                                        name: "mouseleave",
-                                        data: Arc::new(data),
-                                        bubbles: true,
-                                    });
-                                    self.state.cursor_state.hovered = Some(hovered);
-                                }
-                            }
-                            (Some(hovered), None) => {
-                                self.queued_events.push(UserEvent {
-                                    scope_id: None,
-                                    priority: EventPriority::Medium,
-                                    element: Some(hovered),
-                                    name: "mouseenter",
-                                    data: Arc::new(data),
-                                    bubbles: true,
-                                });
-                                self.state.cursor_state.hovered = Some(hovered);
-                            }
-                            (None, Some(old_hovered)) => {
-                                self.queued_events.push(UserEvent {
-                                    scope_id: None,
-                                    priority: EventPriority::Medium,
-                                    element: Some(old_hovered),
-                                    name: "mouseleave",
-                                    data: Arc::new(data),
-                                    bubbles: true,
-                                });
-                                self.state.cursor_state.hovered = None;
-                            }
-                            (None, None) => (),
+                        self.set_hovered(rdom, hovered);
+                        if let Some(hovered) = hovered {
+                            self.push_mouse_event(rdom, hovered, "mousemove", true);
                         }
-                        self.state.cursor_state.position = position;
                     }
                     tao::event::WindowEvent::CursorEntered { device_id: _ } => (),
-                    tao::event::WindowEvent::CursorLeft { device_id: _ } => (),
+                    tao::event::WindowEvent::CursorLeft { device_id: _ } => {
+                        self.set_hovered(rdom, None);
+                    }
                     tao::event::WindowEvent::MouseWheel {
                         device_id: _,
-                        delta: _,
+                        delta,
                         phase: _,
                         ..
-                    } => (),
+                    } => {
+                        // tao reports how far the wheel turned, but the dom reports how far the
+                        // content should move, which is the opposite direction
+                        let (wheel_delta, scroll_delta) = match delta {
+                            MouseScrollDelta::LineDelta(x, y) => {
+                                let (x, y) = (-*x as f64, -*y as f64);
+                                (
+                                    WheelDelta::Lines(LinesVector::new(x, y, 0.0)),
+                                    Vec2::new(x, y) * PIXELS_PER_LINE,
+                                )
+                            }
+                            MouseScrollDelta::PixelDelta(pos) => (
+                                WheelDelta::Pixels(PixelsVector::new(-pos.x, -pos.y, 0.0)),
+                                Vec2::new(-pos.x, -pos.y),
+                            ),
+                            _ => return,
+                        };
+
+                        let client = self.state.cursor_state.position.client();
+                        let target = match self.hit_test.hit(
+                            rdom,
+                            viewport_size,
+                            Point::new(client.x, client.y),
+                        ) {
+                            Some(target) => target,
+                            None => return,
+                        };
+                        // WheelData has no fields for the pointer position, which handlers can
+                        // get from the mousemove events sent before the wheel event instead
+                        self.queued_events.push(UserEvent {
+                            scope_id: None,
+                            priority: EventPriority::Medium,
+                            element: Some(target),
+                            name: "wheel",
+                            data: Arc::new(WheelData::new(wheel_delta)),
+                            bubbles: true,
+                        });
+
+                        if rdom[target].state.prevent_default != PreventDefault::Wheel {
+                            let mut changes = LayoutChanges::default();
+                            if scroll_by(rdom, viewport_size, target, scroll_delta, &mut changes)
+                                .is_some()
+                            {
+                                // scrolling can also clamp the offsets of nested containers
+                                self.queued_events
+                                    .extend(changes.scrolled.into_iter().map(scroll_event));
+                                self.damage.extend(changes.damage);
+                                self.moved = true;
+                                self.hit_test.invalidate();
+                            }
+                        }
+                    }
                     tao::event::WindowEvent::MouseInput {
                         device_id: _,
                         state,
@@ -287,55 +360,20 @@ impl BlitzEventHandler {
                                 _ => todo!(),
                             }
 
-                            let pos = &self.state.cursor_state.position;
-
-                            let data = MouseData::new(
-                                Coordinates::new(
-                                    pos.screen(),
-                                    pos.client(),
-                                    pos.element(),
-                                    pos.page(),
-                                ),
-                                None,
-                                self.state.cursor_state.buttons,
-                                self.state.modifier_state,
-                            );
-
                             let prevent_default = &rdom[hovered].state.prevent_default;
                             match state {
                                 tao::event::ElementState::Pressed => {
-                                    self.queued_events.push(UserEvent {
-                                        scope_id: None,
-                                        priority: EventPriority::Medium,
-                                        element: Some(hovered),
-                                        name: "mousedown",
-                                        data: Arc::new(data),
-                                        bubbles: true,
-                                    });
+                                    self.push_mouse_event(rdom, hovered, "mousedown", true);
                                     self.state.cursor_state.last_pressed_element = Some(hovered);
                                 }
                                 tao::event::ElementState::Released => {
-                                    self.queued_events.push(UserEvent {
-                                        scope_id: None,
-                                        priority: EventPriority::Medium,
-                                        element: Some(hovered),
-                                        name: "mouseup",
-                                        data: Arc::new(data.clone()),
-                                        bubbles: true,
-                                    });
+                                    self.push_mouse_event(rdom, hovered, "mouseup", true);
 
                                     // click events only trigger if the mouse button is pressed and released on the same element
                                     if self.state.cursor_state.last_pressed_element.take()
                                         == Some(hovered)
                                     {
-                                        self.queued_events.push(UserEvent {
-                                            scope_id: None,
-                                            priority: EventPriority::Medium,
-                                            element: Some(hovered),
-                                            name: "click",
-                                            data: Arc::new(data.clone()),
-                                            bubbles: true,
-                                        });
+                                        self.push_mouse_event(rdom, hovered, "click", true);
 
                                         if let Some(last_clicked) =
                                             self.state.cursor_state.last_click.take()
@@ -344,14 +382,9 @@ impl BlitzEventHandler {
                                                 == Some(hovered)
                                                 && last_clicked.elapsed() < DBL_CLICK_TIME
                                             {
-                                                self.queued_events.push(UserEvent {
-                                                    scope_id: None,
-                                                    priority: EventPriority::Medium,
-                                                    element: Some(hovered),
-                                                    name: "dblclick",
-                                                    data: Arc::new(data),
-                                                    bubbles: true,
-                                                });
+                                                self.push_mouse_event(
+                                                    rdom, hovered, "dblclick", true,
+                                                );
                                             }
                                         }
 
@@ -365,12 +398,16 @@ impl BlitzEventHandler {
                             if *prevent_default != PreventDefault::MouseUp
                                 && rdom[hovered].state.focus.level.focusable()
                             {
+                                self.state.keyboard_focus = false;
                                 self.state
                                     .focus_state
                                     .lock()
                                     .unwrap()
                                     .set_focus(rdom, hovered);
                             }
+                        } else if let tao::event::ElementState::Released = state {
+                            // the element stops being active even if the mouse is released outside of it
+                            self.state.cursor_state.last_pressed_element = None;
                         }
                     }
                     tao::event::WindowEvent::TouchpadPressure {
@@ -422,6 +459,111 @@ impl BlitzEventHandler {
         }
     }
 
+    /// Move the hover to a new element and queue the events for the elements the cursor moved
+    /// out of and into.
+    fn set_hovered(&mut self, rdom: &Dom, hovered: Option<ElementId>) {
+        let old_hovered = self.state.cursor_state.hovered;
+        if hovered == old_hovered {
+            return;
+        }
+
+        let old_chain = std::mem::replace(
+            &mut self.state.cursor_state.hovered_chain,
+            element_chain(rdom, hovered),
+        );
+        self.state.cursor_state.hovered = hovered;
+
+        if let Some(old_hovered) = old_hovered {
+            self.push_mouse_event(rdom, old_hovered, "mouseout", true);
+        }
+        // leave from the innermost element outwards
+        for id in &old_chain {
+            if !self.state.cursor_state.hovered_chain.contains(id) {
+                self.push_mouse_event(rdom, *id, "mouseleave", false);
+            }
+        }
+        if let Some(hovered) = hovered {
+            self.push_mouse_event(rdom, hovered, "mouseover", true);
+        }
+        // enter from the outermost element inwards
+        let entered: Vec<_> = self
+            .state
+            .cursor_state
+            .hovered_chain
+            .iter()
+            .rev()
+            .filter(|id| !old_chain.contains(id))
+            .copied()
+            .collect();
+        for id in entered {
+            self.push_mouse_event(rdom, id, "mouseenter", false);
+        }
+    }
+
+    /// Queue a mouse event at the current cursor position for the target and, if it bubbles,
+    /// each of its listening ancestors with the element coordinates relative to their own border
+    /// box.
+    ///
+    /// Each element is sent the event separately, so a handler cancelling the bubbling doesn't
+    /// reach the ancestors. An element stops them with the `dioxus-stop-propagation` attribute.
+    fn push_mouse_event(
+        &mut self,
+        rdom: &Dom,
+        target: ElementId,
+        name: &'static str,
+        bubbles: bool,
+    ) {
+        for id in element_chain(rdom, Some(target)) {
+            let node = &rdom[id];
+            if node.state.listeners.0.contains(&name) {
+                let data = MouseData::new(
+                    self.coordinates_in(node),
+                    None,
+                    self.state.cursor_state.buttons,
+                    self.state.modifier_state,
+                );
+                // the ancestors are sent their own event
+                self.queued_events.push(UserEvent {
+                    scope_id: None,
+                    priority: EventPriority::Medium,
+                    element: Some(id),
+                    name,
+                    data: Arc::new(data),
+                    bubbles: false,
+                });
+                if node.state.stop_propagation.stops(name) {
+                    break;
+                }
+            }
+            if !bubbles {
+                break;
+            }
+        }
+    }
+
+    /// The coordinates of the cursor with the element coordinates relative to a node.
+    fn coordinates_in(&self, node: &DomNode) -> Coordinates {
+        let pos = &self.state.cursor_state.position;
+        let client = pos.client();
+        let origin = node.state.layout.boxes.border.origin();
+        Coordinates::new(
+            pos.screen(),
+            client,
+            ElementPoint::new(client.x - origin.x, client.y - origin.y),
+            pos.page(),
+        )
+    }
+
+    /// The state of the elements that pseudo-classes like `:hover` match against.
+    pub(crate) fn element_states(&self, rdom: &Dom) -> ElementStates {
+        ElementStates {
+            hovered: self.state.cursor_state.hovered_chain.clone(),
+            active: element_chain(rdom, self.state.cursor_state.last_pressed_element),
+            focused: self.state.focus_state.lock().unwrap().last_focused_id,
+            focus_visible: self.state.keyboard_focus,
+        }
+    }
+
     pub fn drain_events(&mut self) -> Vec<UserEvent> {
         let mut events = Vec::new();
         std::mem::swap(&mut self.queued_events, &mut events);
@@ -434,6 +576,10 @@ impl BlitzEventHandler {
                 self.state.cursor_state.hovered = None;
             }
         }
+        self.state
+            .cursor_state
+            .hovered_chain
+            .retain(|id| *id != removed);
         if let Some(id) = self.state.cursor_state.last_pressed_element {
             if id == removed {
                 self.state.cursor_state.last_pressed_element = None;
@@ -468,7 +614,136 @@ impl BlitzEventHandler {
         }
     }
 
-    pub(crate) fn clean(&self) -> bool {
-        self.state.focus_state.lock().unwrap().clean()
+    /// Forget where elements are after the layout changed.
+    pub(crate) fn invalidate_layout(&mut self) {
+        self.hit_test.invalidate();
+    }
+
+    /// If elements were moved by scrolling since the last call.
+    pub(crate) fn take_moved(&mut self) -> bool {
+        std::mem::take(&mut self.moved)
+    }
+
+    /// If the window needs to be repainted because of something the events changed.
+    pub(crate) fn needs_redraw(&self) -> bool {
+        !self.damage.is_empty() || self.state.focus_state.lock().unwrap().dirty
+    }
+
+    /// Add the area of a node that is painted differently to the next frame.
+    pub(crate) fn damage_node(&mut self, rdom: &Dom, id: ElementId) {
+        self.damage.add_node(rdom, id);
+    }
+
+    /// Take the area the events changed since the last frame.
+    pub(crate) fn take_damage(&mut self) -> Damage {
+        self.state.focus_state.lock().unwrap().clean();
+        std::mem::take(&mut self.damage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dioxus::prelude::*;
+    use dioxus_native_core::real_dom::RealDom;
+    use taffy::prelude::Number;
+
+    use super::*;
+    use crate::{
+        application::update_state,
+        image::ImageLoader,
+        layout::{compute_layout, LayoutContext},
+        stylesheet::{Cascade, DEFAULT_STYLESHEET},
+    };
+
+    fn laid_out(app: Component<()>) -> Dom {
+        let mut vdom = VirtualDom::new(app);
+        let mut rdom: Dom = RealDom::new();
+        let ctx = LayoutContext::new(
+            Cascade::new(Some(DEFAULT_STYLESHEET), &[]),
+            ImageLoader::new(Vec::new(), || {}),
+        )
+        .expect("failed to create the layout context");
+        let mutations = vec![vdom.rebuild()];
+        update_state(&mut rdom, &vdom, mutations, &ctx, &ElementStates::default());
+        let size = Size {
+            width: Number::Defined(100.0),
+            height: Number::Defined(100.0),
+        };
+        compute_layout(&mut rdom, &ctx, size);
+        rdom
+    }
+
+    /// Send a click at a point in the window to an element and return the elements that got it
+    /// with their element coordinates.
+    fn click(rdom: &Dom, target: ElementId, x: f64, y: f64) -> Vec<(ElementId, (f64, f64))> {
+        let mut handler = BlitzEventHandler::default();
+        handler.state.cursor_state.position = Coordinates::new(
+            ScreenPoint::new(x, y),
+            ClientPoint::new(x, y),
+            ElementPoint::new(x, y),
+            PagePoint::new(x, y),
+        );
+        handler.push_mouse_event(rdom, target, "click", true);
+        handler
+            .drain_events()
+            .into_iter()
+            .map(|event| {
+                let data = event.data.downcast_ref::<MouseData>().unwrap();
+                let point = data.element_coordinates();
+                (event.element.unwrap(), (point.x, point.y))
+            })
+            .collect()
+    }
+
+    fn first_child(rdom: &Dom, id: ElementId) -> ElementId {
+        match &rdom[id].node_type {
+            NodeType::Element { children, .. } => children[0],
+            _ => panic!("not an element"),
+        }
+    }
+
+    #[test]
+    fn gives_each_element_coordinates_in_its_own_border_box() {
+        fn app(cx: Scope) -> Element {
+            cx.render(rsx! {
+                div {
+                    style: "margin: 20px 0px 0px 10px; padding: 5px;",
+                    onclick: |_| {},
+                    div {
+                        style: "width: 30px; height: 30px;",
+                        onclick: |_| {},
+                        div { style: "width: 10px; height: 10px;" }
+                    }
+                }
+            })
+        }
+        let rdom = laid_out(app);
+        let parent = first_child(&rdom, ElementId(rdom.root_id()));
+        let child = first_child(&rdom, parent);
+        // elements without a listener are skipped
+        let grandchild = first_child(&rdom, child);
+        assert_eq!(
+            click(&rdom, grandchild, 40.0, 50.0),
+            [(child, (25.0, 25.0)), (parent, (30.0, 30.0))]
+        );
+    }
+
+    #[test]
+    fn stops_at_elements_that_stop_the_propagation() {
+        fn app(cx: Scope) -> Element {
+            cx.render(rsx! {
+                div {
+                    onclick: |_| {},
+                    div {
+                        "dioxus-stop-propagation": "onclick",
+                        onclick: |_| {},
+                    }
+                }
+            })
+        }
+        let rdom = laid_out(app);
+        let parent = first_child(&rdom, ElementId(rdom.root_id()));
+        let child = first_child(&rdom, parent);
+        assert_eq!(click(&rdom, child, 0.0, 0.0), [(child, (0.0, 0.0))]);
     }
 }
@@ -0,0 +1,572 @@
+use parcel_css::values::angle::Angle;
+use parcel_css::values::gradient::{
+    Circle, ConicGradient, Ellipse, EndingShape, Gradient, GradientItem, LineDirection,
+    LinearGradient, RadialGradient, ShapeExtent,
+};
+use parcel_css::values::image::Image;
+use parcel_css::values::percentage::DimensionPercentage;
+use parcel_css::values::position::{HorizontalPositionKeyword, Position, VerticalPositionKeyword};
+use piet_wgpu::kurbo::{Affine, BezPath, Circle, Point, Rect, Shape, Vec2};
+use piet_wgpu::{Color, FixedLinearGradient, FixedRadialGradient, GradientStop, RenderContext};
+use taffy::prelude::Size;
+
+use crate::util::{resolve_color, resolve_position, Axis, Resolve};
+use crate::DomNode;
+
+/// How many slices a conic gradient is drawn with.
+const CONIC_SLICES: usize = 360;
+
+/// A color stop with its position resolved to a fraction of the gradient line.
+#[derive(Clone, Debug)]
+struct Stop {
+    offset: f64,
+    color: Color,
+}
+
+/// Paint the background gradients of an element inside the shape the background is clipped to.
+pub(crate) fn render_background_images(
+    node: &DomNode,
+    piet: &mut impl RenderContext,
+    clip: &BezPath,
+    viewport_size: &Size<u32>,
+) {
+    let images = &node.state.style.background.images;
+    let area = node.state.layout.boxes.padding;
+    if images.is_empty() || area.width() <= 0.0 || area.height() <= 0.0 {
+        return;
+    }
+    let painter = Painter {
+        node,
+        area,
+        bounds: clip.bounding_box(),
+        viewport_size,
+    };
+    piet.save().unwrap();
+    piet.clip(clip.clone());
+    // the first layer is on top
+    for image in images.iter().rev() {
+        if let Image::Gradient(gradient) = image {
+            painter.paint(piet, gradient);
+        }
+    }
+    piet.restore().unwrap();
+}
+
+struct Painter<'a> {
+    node: &'a DomNode,
+    /// The box the gradient is sized to.
+    area: Rect,
+    /// The area that needs to be covered.
+    bounds: Rect,
+    viewport_size: &'a Size<u32>,
+}
+
+impl Painter<'_> {
+    fn paint(&self, piet: &mut impl RenderContext, gradient: &Gradient) {
+        match gradient {
+            Gradient::Linear(linear, ..) => self.linear(piet, linear, false),
+            Gradient::RepeatingLinear(linear, ..) => self.linear(piet, linear, true),
+            Gradient::Radial(radial, ..) => self.radial(piet, radial, false),
+            Gradient::RepeatingRadial(radial, ..) => self.radial(piet, radial, true),
+            Gradient::Conic(conic, ..) => self.conic(piet, conic, false),
+            Gradient::RepeatingConic(conic, ..) => self.conic(piet, conic, true),
+            // the legacy -webkit-gradient() syntax isn't supported
+            _ => {}
+        }
+    }
+
+    fn linear(&self, piet: &mut impl RenderContext, gradient: &LinearGradient, repeating: bool) {
+        let (w, h) = (self.area.width(), self.area.height());
+        let direction = match &gradient.direction {
+            LineDirection::Angle(angle) => Vec2::from_angle(angle_from_top(angle)),
+            LineDirection::Horizontal(HorizontalPositionKeyword::Left) => Vec2::new(-1.0, 0.0),
+            LineDirection::Horizontal(HorizontalPositionKeyword::Right) => Vec2::new(1.0, 0.0),
+            LineDirection::Vertical(VerticalPositionKeyword::Top) => Vec2::new(0.0, -1.0),
+            LineDirection::Vertical(VerticalPositionKeyword::Bottom) => Vec2::new(0.0, 1.0),
+            // the line points to the corner, at a right angle to the diagonal between the other
+            // two corners
+            LineDirection::Corner {
+                horizontal,
+                vertical,
+            } => {
+                let x = match horizontal {
+                    HorizontalPositionKeyword::Left => -h,
+                    HorizontalPositionKeyword::Right => h,
+                };
+                let y = match vertical {
+                    VerticalPositionKeyword::Top => -w,
+                    VerticalPositionKeyword::Bottom => w,
+                };
+                Vec2::new(x, y).normalize()
+            }
+        };
+        // the line is long enough for the lines at a right angle to its ends to touch the corners
+        let length = (w * direction.x).abs() + (h * direction.y).abs();
+        if length <= 0.0 {
+            return;
+        }
+        let stops = self.stops(&gradient.items, |p| {
+            let size = Size {
+                width: length as f32,
+                height: length as f32,
+            };
+            Some(p.resolve(Axis::X, &size, self.viewport_size) / length)
+        });
+        if stops.is_empty() {
+            return;
+        }
+        let center = self.area.center();
+        if repeating {
+            // the gradient line is the x axis, starting at zero
+            let transform = Affine::translate((center - direction * (length / 2.0)).to_vec2())
+                * Affine::rotate(direction.atan2());
+            self.tile_linear(piet, stops, transform, length);
+            return;
+        }
+        let stops = fit(stops, 0.0, 1.0);
+        let brush = piet
+            .gradient(FixedLinearGradient {
+                start: center - direction * (length / 2.0),
+                end: center + direction * (length / 2.0),
+                stops: gradient_stops(&stops, 0.0, 1.0),
+            })
+            .unwrap();
+        piet.fill(self.bounds, &brush);
+    }
+
+    fn radial(&self, piet: &mut impl RenderContext, gradient: &RadialGradient, repeating: bool) {
+        let center = self.position(&gradient.position);
+        let (left, top) = (center.x - self.area.x0, center.y - self.area.y0);
+        let (right, bottom) = (self.area.x1 - center.x, self.area.y1 - center.y);
+        let (left, right, top, bottom) = (left.abs(), right.abs(), top.abs(), bottom.abs());
+        let corners = [
+            Vec2::new(left, top),
+            Vec2::new(right, top),
+            Vec2::new(right, bottom),
+            Vec2::new(left, bottom),
+        ];
+        let size = Size {
+            width: self.area.width() as f32,
+            height: self.area.height() as f32,
+        };
+        let radii = match &gradient.shape {
+            EndingShape::Circle(Circle::Radius(r)) => {
+                let r = r.resolve(Axis::Min, &size, self.viewport_size);
+                Vec2::new(r, r)
+            }
+            EndingShape::Circle(Circle::Extent(extent)) => {
+                let r = match extent {
+                    ShapeExtent::ClosestSide => left.min(right).min(top).min(bottom),
+                    ShapeExtent::FarthestSide => left.max(right).max(top).max(bottom),
+                    ShapeExtent::ClosestCorner => {
+                        corners.iter().map(|c| c.hypot()).fold(f64::MAX, f64::min)
+                    }
+                    ShapeExtent::FarthestCorner => {
+                        corners.iter().map(|c| c.hypot()).fold(0.0, f64::max)
+                    }
+                };
+                Vec2::new(r, r)
+            }
+            EndingShape::Ellipse(Ellipse::Size(x, y)) => Vec2::new(
+                x.resolve(Axis::X, &size, self.viewport_size),
+                y.resolve(Axis::Y, &size, self.viewport_size),
+            ),
+            // an ellipse through a corner has the aspect ratio of the one touching the sides,
+            // so it is that ellipse scaled by the square root of two
+            EndingShape::Ellipse(Ellipse::Extent(extent)) => match extent {
+                ShapeExtent::ClosestSide => Vec2::new(left.min(right), top.min(bottom)),
+                ShapeExtent::FarthestSide => Vec2::new(left.max(right), top.max(bottom)),
+                ShapeExtent::ClosestCorner => {
+                    Vec2::new(left.min(right), top.min(bottom)) * std::f64::consts::SQRT_2
+                }
+                ShapeExtent::FarthestCorner => {
+                    Vec2::new(left.max(right), top.max(bottom)) * std::f64::consts::SQRT_2
+                }
+            },
+        };
+        if radii.x <= 0.0 || radii.y <= 0.0 {
+            return;
+        }
+        let stops = self.stops(&gradient.items, |p| {
+            let size = Size {
+                width: radii.x as f32,
+                height: radii.x as f32,
+            };
+            Some(p.resolve(Axis::X, &size, self.viewport_size) / radii.x)
+        });
+        if stops.is_empty() {
+            return;
+        }
+        // draw a circle of radius one and stretch it to the ellipse
+        let transform =
+            Affine::translate(center.to_vec2()) * Affine::scale_non_uniform(radii.x, radii.y);
+        let reach = corners
+            .iter()
+            .map(|c| Vec2::new(c.x / radii.x, c.y / radii.y).hypot())
+            .fold(1.0, f64::max);
+        if repeating {
+            // a pixel in the space of the circle, measured along the shorter radius
+            let pixel = 1.0 / radii.x.min(radii.y);
+            self.tile_radial(piet, stops, transform, reach, pixel);
+            return;
+        }
+        let stops = fit(stops, 0.0, reach);
+        let brush = piet
+            .gradient(FixedRadialGradient {
+                center: Point::ORIGIN,
+                origin_offset: Vec2::ZERO,
+                radius: reach,
+                stops: gradient_stops(&stops, 0.0, reach),
+            })
+            .unwrap();
+        piet.save().unwrap();
+        piet.transform(transform);
+        piet.fill(transform.inverse() * self.bounds.to_path(0.1), &brush);
+        piet.restore().unwrap();
+    }
+
+    fn conic(&self, piet: &mut impl RenderContext, gradient: &ConicGradient, repeating: bool) {
+        let center = self.position(&gradient.position);
+        let start = angle_from_top(&gradient.angle);
+        let stops = self.stops(&gradient.items, |p| match p {
+            DimensionPercentage::Dimension(angle) => {
+                Some(angle.to_radians() as f64 / std::f64::consts::TAU)
+            }
+            DimensionPercentage::Percentage(p) => Some(p.0 as f64),
+            // treated like a stop without a position
+            DimensionPercentage::Calc(_) => None,
+        });
+        if stops.is_empty() {
+            return;
+        }
+        // repeating gradients are sampled within their first period
+        let period = repeating.then(|| Period::new(stops.clone()));
+        // piet has no conic gradients, so the circle is cut into thin slices of solid color
+        let radius = corners(self.bounds)
+            .iter()
+            .map(|c| c.distance(center))
+            .fold(0.0, f64::max)
+            + 1.0;
+        let slice = std::f64::consts::TAU / CONIC_SLICES as f64;
+        for i in 0..CONIC_SLICES {
+            // overlap the next slice a bit so no seams show between them
+            let from = start + i as f64 * slice;
+            let to = from + slice * 1.5;
+            let mut path = BezPath::new();
+            path.move_to(center);
+            path.line_to(center + Vec2::from_angle(from) * radius);
+            path.line_to(center + Vec2::from_angle(to) * radius);
+            path.close_path();
+            if path.bounding_box().intersect(self.bounds).area() <= 0.0 {
+                continue;
+            }
+            let offset = (i as f64 + 0.5) / CONIC_SLICES as f64;
+            let color = match &period {
+                Some(Ok(period)) => period.sample(offset),
+                Some(Err(color)) => color.clone(),
+                None => sample(&stops, offset),
+            };
+            let brush = piet.solid_brush(color);
+            piet.fill(path, &brush);
+        }
+    }
+
+    /// Paint a repeating linear gradient one period at a time. The gradient line is the x axis
+    /// of `transform`, from zero to `length`.
+    fn tile_linear(
+        &self,
+        piet: &mut impl RenderContext,
+        stops: Vec<Stop>,
+        transform: Affine,
+        length: f64,
+    ) {
+        let period = match Period::new(stops) {
+            Ok(period) => period,
+            Err(color) => {
+                let brush = piet.solid_brush(color);
+                piet.fill(self.bounds, &brush);
+                return;
+            }
+        };
+        // the part of the line next to the bounds and how far the bounds reach to its sides
+        let corners = corners(self.bounds).map(|c| transform.inverse() * c);
+        let from = corners.iter().map(|c| c.x).fold(f64::MAX, f64::min) / length;
+        let to = corners.iter().map(|c| c.x).fold(f64::MIN, f64::max) / length;
+        let reach = corners.iter().map(|c| c.y.abs()).fold(0.0, f64::max) + 1.0;
+
+        let (start, end) = (period.start, period.start + period.length);
+        let brush = piet
+            .gradient(FixedLinearGradient {
+                start: Point::new(start * length, 0.0),
+                end: Point::new(end * length, 0.0),
+                stops: gradient_stops(&period.stops, start, end),
+            })
+            .unwrap();
+        piet.save().unwrap();
+        piet.transform(transform);
+        for k in period.covering(from, to) {
+            piet.save().unwrap();
+            piet.transform(Affine::translate((k as f64 * period.length * length, 0.0)));
+            // overlap the next period a bit so no seams show between them
+            let tile = Rect::new(start * length, -reach, end * length + 1.0, reach);
+            piet.fill(tile, &brush);
+            piet.restore().unwrap();
+        }
+        piet.restore().unwrap();
+    }
+
+    /// Paint a repeating radial gradient one ring at a time, from the inside out. The gradient
+    /// is a circle of radius one at the origin of `transform` and reaches the corners at
+    /// `reach`.
+    fn tile_radial(
+        &self,
+        piet: &mut impl RenderContext,
+        stops: Vec<Stop>,
+        transform: Affine,
+        reach: f64,
+        pixel: f64,
+    ) {
+        let period = match Period::new(stops) {
+            Ok(period) => period,
+            Err(color) => {
+                let brush = piet.solid_brush(color);
+                piet.fill(self.bounds, &brush);
+                return;
+            }
+        };
+        piet.save().unwrap();
+        piet.transform(transform);
+        for k in period.covering(0.0, reach) {
+            let shift = k as f64 * period.length;
+            let inner = (period.start + shift).max(0.0);
+            let outer = period.start + period.length + shift;
+            if outer <= 0.0 {
+                continue;
+            }
+            let stops = period
+                .stops
+                .iter()
+                .map(|s| Stop {
+                    offset: s.offset + shift,
+                    color: s.color.clone(),
+                })
+                .collect();
+            let brush = piet
+                .gradient(FixedRadialGradient {
+                    center: Point::ORIGIN,
+                    origin_offset: Vec2::ZERO,
+                    radius: outer,
+                    stops: gradient_stops(&fit(stops, inner, outer), 0.0, outer),
+                })
+                .unwrap();
+            // overlap the next ring a bit so no seams show between them
+            let mut ring = Circle::new(Point::ORIGIN, outer + pixel).to_path(0.1);
+            if inner > 0.0 {
+                ring.extend(Circle::new(Point::ORIGIN, inner).to_path(0.1));
+            }
+            piet.fill_even_odd(ring, &brush);
+        }
+        piet.restore().unwrap();
+    }
+
+    /// Where a position is in the area.
+    fn position(&self, position: &Position) -> Point {
+        let size = Size {
+            width: self.area.width() as f32,
+            height: self.area.height() as f32,
+        };
+        self.area.origin() + resolve_position(position, &size, self.viewport_size)
+    }
+
+    /// Resolve the color stops and hints of a gradient, with `resolve` turning a position into
+    /// a fraction of the gradient line.
+    fn stops<D>(
+        &self,
+        items: &[GradientItem<D>],
+        resolve: impl Fn(&D) -> Option<f64>,
+    ) -> Vec<Stop> {
+        let current_color = &self.node.state.style.color.0;
+        // hints are the entries without a color
+        let mut entries: Vec<(Option<f64>, Option<Color>)> = items
+            .iter()
+            .map(|item| match item {
+                GradientItem::ColorStop(stop) => (
+                    stop.position.as_ref().and_then(&resolve),
+                    Some(resolve_color(&stop.color, current_color)),
+                ),
+                GradientItem::Hint(hint) => (resolve(hint), None),
+            })
+            .filter(|(offset, color)| offset.is_some() || color.is_some())
+            .collect();
+        if entries.is_empty() {
+            return Vec::new();
+        }
+        let last = entries.len() - 1;
+        entries[0].0.get_or_insert(0.0);
+        entries[last].0.get_or_insert(1.0);
+
+        let mut max = f64::MIN;
+        for (offset, _) in &mut entries {
+            if let Some(offset) = offset {
+                *offset = offset.max(max);
+                max = *offset;
+            }
+        }
+        let mut i = 0;
+        while i < entries.len() {
+            if entries[i].0.is_some() {
+                i += 1;
+                continue;
+            }
+            // the first and the last entry always have a position
+            let before = entries[i - 1].0.unwrap();
+            let end = (i..entries.len())
+                .find(|j| entries[*j].0.is_some())
+                .unwrap();
+            let after = entries[end].0.unwrap();
+            let count = (end - i + 1) as f64;
+            for (n, j) in (i..end).enumerate() {
+                entries[j].0 = Some(before + (after - before) * (n + 1) as f64 / count);
+            }
+            i = end;
+        }
+
+        let mut stops = Vec::with_capacity(entries.len());
+        for (i, (offset, color)) in entries.iter().enumerate() {
+            let offset = offset.unwrap();
+            match color {
+                Some(color) => stops.push(Stop {
+                    offset,
+                    color: color.clone(),
+                }),
+                // the color halfway between the stops around a hint is at the hint
+                None => {
+                    let before = entries[..i].iter().rev().find_map(|e| e.1.as_ref());
+                    let after = entries[i + 1..].iter().find_map(|e| e.1.as_ref());
+                    if let (Some(before), Some(after)) = (before, after) {
+                        stops.push(Stop {
+                            offset,
+                            color: mix(before, after, 0.5),
+                        });
+                    }
+                }
+            }
+        }
+        stops
+    }
+}
+
+/// Css angles start at the top and go clockwise, kurbo angles start at the right and go
+/// clockwise on the screen.
+fn angle_from_top(angle: &Angle) -> f64 {
+    angle.to_radians() as f64 - std::f64::consts::FRAC_PI_2
+}
+
+/// Fit the stops to the part of the gradient line between two offsets, with stops at both ends
+/// with the colors the gradient has there.
+fn fit(stops: Vec<Stop>, from: f64, to: f64) -> Vec<Stop> {
+    let mut fitted = vec![Stop {
+        offset: from,
+        color: sample(&stops, from),
+    }];
+    fitted.extend(
+        stops
+            .iter()
+            .filter(|s| s.offset > from && s.offset < to)
+            .cloned(),
+    );
+    fitted.push(Stop {
+        offset: to,
+        color: sample(&stops, to),
+    });
+    fitted
+}
+
+/// The stops of a repeating gradient, which repeat every `length` from `start` on in both
+/// directions.
+struct Period {
+    start: f64,
+    length: f64,
+    stops: Vec<Stop>,
+}
+
+impl Period {
+    /// Fails with the color to paint instead if all the stops are at the same offset.
+    fn new(stops: Vec<Stop>) -> Result<Self, Color> {
+        let (first, last) = (stops[0].offset, stops[stops.len() - 1].offset);
+        if last - first <= f64::EPSILON {
+            // css paints the average color, the last color is close enough
+            return Err(stops[stops.len() - 1].color.clone());
+        }
+        Ok(Self {
+            start: first,
+            length: last - first,
+            stops,
+        })
+    }
+
+    /// The periods that cover the part of the gradient line between two offsets, by how many
+    /// periods they are moved from the first one.
+    fn covering(&self, from: f64, to: f64) -> std::ops::Range<i64> {
+        let first = ((from - self.start) / self.length).floor() as i64;
+        let last = ((to - self.start) / self.length).ceil() as i64;
+        first..last
+    }
+
+    fn sample(&self, offset: f64) -> Color {
+        sample(
+            &self.stops,
+            self.start + (offset - self.start).rem_euclid(self.length),
+        )
+    }
+}
+
+/// The corners of a rectangle, clockwise from the top left.
+fn corners(rect: Rect) -> [Point; 4] {
+    [
+        Point::new(rect.x0, rect.y0),
+        Point::new(rect.x1, rect.y0),
+        Point::new(rect.x1, rect.y1),
+        Point::new(rect.x0, rect.y1),
+    ]
+}
+
+/// The color of the gradient at an offset.
+fn sample(stops: &[Stop], offset: f64) -> Color {
+    let after = match stops.iter().position(|s| s.offset > offset) {
+        Some(after) => after,
+        None => return stops.last().map_or(Color::TRANSPARENT, |s| s.color.clone()),
+    };
+    if after == 0 {
+        return stops[0].color.clone();
+    }
+    let (a, b) = (&stops[after - 1], &stops[after]);
+    mix(
+        &a.color,
+        &b.color,
+        (offset - a.offset) / (b.offset - a.offset),
+    )
+}
+
+/// Interpolate between two colors with premultiplied alpha like css does, so fading to
+/// transparent doesn't darken the color.
+fn mix(a: &Color, b: &Color, t: f64) -> Color {
+    let (ar, ag, ab, aa) = a.as_rgba();
+    let (br, bg, bb, ba) = b.as_rgba();
+    let alpha = aa + (ba - aa) * t;
+    if alpha <= 0.0 {
+        return Color::TRANSPARENT;
+    }
+    let channel = |x: f64, y: f64| (x * aa + (y * ba - x * aa) * t) / alpha;
+    Color::rgba(channel(ar, br), channel(ag, bg), channel(ab, bb), alpha)
+}
+
+fn gradient_stops(stops: &[Stop], from: f64, to: f64) -> Vec<GradientStop> {
+    stops
+        .iter()
+        .map(|s| GradientStop {
+            pos: ((s.offset - from) / (to - from)) as f32,
+            color: s.color.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,157 @@
+use std::path::Path;
+
+use dioxus::core::{Mutations, SchedulerMsg, UserEvent};
+use dioxus::prelude::{Component, VirtualDom};
+use dioxus_native_core::real_dom::RealDom;
+use piet_common::{BitmapTarget, Color, ImageFormat};
+use taffy::prelude::{Number, Size};
+use tao::dpi::PhysicalSize;
+
+use crate::{
+    application::update_state,
+    image::{ImageLoader, LoadObserver},
+    layout::{compute_layout, LayoutContext},
+    query::RectObserver,
+    render::{Damage, RetainedFrame},
+    resize::ResizeObserver,
+    scroll::scroll_event,
+    stylesheet::{Cascade, ElementStates},
+    Config, Dom,
+};
+
+/// Renders a VirtualDom without a window or GPU into an in-memory RGBA buffer.
+pub struct HeadlessApp {
+    vdom: VirtualDom,
+    rdom: Dom,
+    layout_ctx: LayoutContext,
+    rects: RectObserver,
+    resize: ResizeObserver,
+    loads: LoadObserver,
+    size: PhysicalSize<u32>,
+    frame: RetainedFrame,
+    /// The area that changed since the last frame.
+    damage: Damage,
+}
+
+impl HeadlessApp {
+    /// Build the VirtualDom and lay it out for a viewport of the given size.
+    ///
+    /// Fails if the CPU rasterizer can't be created, e.g. because cairo is not installed.
+    pub fn new(root: Component<()>, width: u32, height: u32) -> Result<Self, piet_common::Error> {
+        Self::new_cfg(root, width, height, Config::default())
+    }
+
+    pub fn new_cfg(
+        root: Component<()>,
+        width: u32,
+        height: u32,
+        cfg: Config,
+    ) -> Result<Self, piet_common::Error> {
+        let vdom = VirtualDom::new(root);
+        let rects = RectObserver::provide(&vdom);
+        let mut app = HeadlessApp {
+            vdom,
+            rdom: RealDom::new(),
+            layout_ctx: LayoutContext::new(
+                Cascade::new(cfg.default_stylesheet.as_deref(), &cfg.stylesheets),
+                // finished images are picked up by the next update
+                ImageLoader::new(cfg.resource_loaders, || {}),
+            )?,
+            rects,
+            resize: ResizeObserver::default(),
+            loads: LoadObserver::default(),
+            size: PhysicalSize::new(width, height),
+            frame: RetainedFrame::new()?,
+            damage: Damage::default(),
+        };
+        let mutations = app.vdom.rebuild();
+        app.apply_mutations(vec![mutations]);
+        Ok(app)
+    }
+
+    /// Process any pending work in the VirtualDom and the images that finished loading without
+    /// waiting.
+    pub fn update(&mut self) {
+        let mutations = self.vdom.work_with_deadline(|| false);
+        self.apply_mutations(mutations);
+    }
+
+    /// Wait for the VirtualDom to have work (e.g. a future resolving) and then process it.
+    pub async fn wait_for_work(&mut self) {
+        self.vdom.wait_for_work().await;
+        self.update();
+    }
+
+    /// Block until every image that started loading has finished, then lay them out. Their
+    /// load handlers run on the next update.
+    pub fn wait_for_images(&mut self) {
+        self.layout_ctx.images.wait();
+        self.update();
+    }
+
+    /// Queue an event for the VirtualDom. It is handled on the next [`HeadlessApp::update`].
+    pub fn send_event(&mut self, event: UserEvent) {
+        self.vdom.handle_message(SchedulerMsg::Event(event));
+    }
+
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.size = PhysicalSize::new(width, height);
+        self.layout();
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// Render the current state of the dom into a buffer of non-premultiplied RGBA pixels, row by row.
+    pub fn render_rgba(&mut self) -> Result<Vec<u8>, piet_common::Error> {
+        let mut buffer = vec![0; self.size.width as usize * self.size.height as usize * 4];
+        let mut target = self.paint()?;
+        target.copy_raw_pixels(ImageFormat::RgbaSeparate, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Render the current state of the dom and save it as a PNG.
+    pub fn save_png(&mut self, path: impl AsRef<Path>) -> Result<(), piet_common::Error> {
+        self.paint()?.save_to_file(path)
+    }
+
+    fn paint(&mut self) -> Result<BitmapTarget<'_>, piet_common::Error> {
+        self.frame
+            .paint(&self.rdom, self.size, &mut self.damage, Color::WHITE)
+    }
+
+    fn apply_mutations(&mut self, mutations: Vec<Mutations>) {
+        self.damage.add_removed(&self.rdom, &mutations);
+        // there is no mouse or keyboard, so no element is hovered or focused
+        let to_rerender = update_state(
+            &mut self.rdom,
+            &self.vdom,
+            mutations,
+            &self.layout_ctx,
+            &ElementStates::default(),
+        );
+        self.layout();
+        for id in to_rerender {
+            self.damage.add_node(&self.rdom, id);
+        }
+    }
+
+    fn layout(&mut self) {
+        let size = Size {
+            width: Number::Defined(self.size.width as f32),
+            height: Number::Defined(self.size.height as f32),
+        };
+        let changes = compute_layout(&mut self.rdom, &self.layout_ctx, size);
+        self.damage.extend(changes.damage);
+        for id in changes.scrolled {
+            self.vdom
+                .handle_message(SchedulerMsg::Event(scroll_event(id)));
+        }
+        self.rects.update(&self.rdom, &self.vdom);
+        let events = self.resize.update(&self.rdom);
+        for event in events.into_iter().chain(self.loads.update(&self.rdom)) {
+            self.vdom.handle_message(SchedulerMsg::Event(event));
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+
+use dioxus::core::{ElementId, EventPriority, UserEvent};
+use dioxus::events::ImageData;
+use dioxus_native_core::node_ref::NodeMask;
+use fxhash::{FxHashMap, FxHashSet};
+
+use crate::Dom;
+
+/// How many images are loaded at the same time.
+const WORKERS: usize = 4;
+
+/// Loads the bytes of resources like the `src` of an `img` for schemes other than `file:` and
+/// `data:`. Loaders run on background threads, so they can block.
+pub trait ResourceLoader: Send + Sync {
+    /// Load the resource at `src`, or return `None` if this loader doesn't handle it.
+    fn load(&self, src: &str) -> Option<Result<Vec<u8>, String>>;
+}
+
+/// An image decoded into non-premultiplied RGBA pixels, row by row.
+#[derive(Debug)]
+pub(crate) struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// How far loading an image has come.
+#[derive(Clone, Debug)]
+pub(crate) enum ImageState {
+    Loading,
+    Loaded(Arc<DecodedImage>),
+    Failed(Arc<str>),
+}
+
+impl PartialEq for ImageState {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ImageState::Loading, ImageState::Loading) => true,
+            (ImageState::Loaded(a), ImageState::Loaded(b)) => Arc::ptr_eq(a, b),
+            (ImageState::Failed(a), ImageState::Failed(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// The image an `img` element shows.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct ImageSource {
+    pub src: String,
+    pub state: ImageState,
+}
+
+#[derive(Default)]
+struct ImageLoaderInner {
+    images: FxHashMap<String, ImageState>,
+    /// The images waiting for a worker.
+    queue: VecDeque<String>,
+    /// How many workers are running.
+    workers: usize,
+    /// The images that finished loading since the last update.
+    finished: Vec<String>,
+}
+
+/// Loads and decodes images in the background and shares them between the elements that show
+/// them.
+#[derive(Clone)]
+pub(crate) struct ImageLoader {
+    inner: Arc<Mutex<ImageLoaderInner>>,
+    /// Notified whenever an image finished loading.
+    loaded: Arc<Condvar>,
+    loaders: Arc<[Arc<dyn ResourceLoader>]>,
+    /// Called from a background thread when an image finished loading.
+    wake: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl ImageLoader {
+    pub(crate) fn new(
+        loaders: Vec<Arc<dyn ResourceLoader>>,
+        wake: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            inner: Arc::default(),
+            loaded: Arc::default(),
+            loaders: loaders.into(),
+            wake: Arc::new(wake),
+        }
+    }
+
+    /// Get the state of an image and start loading it if this is the first time it is used.
+    pub(crate) fn get(&self, src: &str) -> ImageState {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(state) = inner.images.get(src) {
+            return state.clone();
+        }
+        inner.images.insert(src.to_string(), ImageState::Loading);
+        inner.queue.push_back(src.to_string());
+        if inner.workers < WORKERS {
+            inner.workers += 1;
+            let this = self.clone();
+            std::thread::spawn(move || this.work());
+        }
+        ImageState::Loading
+    }
+
+    /// Load the queued images until the queue is empty.
+    fn work(&self) {
+        loop {
+            let src = {
+                let mut inner = self.inner.lock().unwrap();
+                match inner.queue.pop_front() {
+                    Some(src) => src,
+                    None => {
+                        inner.workers -= 1;
+                        return;
+                    }
+                }
+            };
+            let state = match load(&src, &self.loaders).and_then(|bytes| decode(&bytes)) {
+                Ok(image) => ImageState::Loaded(Arc::new(image)),
+                Err(err) => ImageState::Failed(err.into()),
+            };
+            let mut inner = self.inner.lock().unwrap();
+            inner.images.insert(src.clone(), state);
+            inner.finished.push(src);
+            drop(inner);
+            self.loaded.notify_all();
+            (self.wake)();
+        }
+    }
+
+    /// Block until every image that started loading has finished.
+    pub(crate) fn wait(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        while inner
+            .images
+            .values()
+            .any(|state| matches!(state, ImageState::Loading))
+        {
+            inner = self.loaded.wait(inner).unwrap();
+        }
+    }
+
+    /// Drop the images no element shows any more and return the elements showing images that
+    /// finished loading since the last update.
+    pub(crate) fn update(&self, rdom: &Dom) -> Vec<(ElementId, NodeMask)> {
+        let mut inner = self.inner.lock().unwrap();
+        let finished: FxHashSet<String> = std::mem::take(&mut inner.finished).into_iter().collect();
+        // the elements hand the images they show to the nodes after this update, so only the
+        // ones that finished before it can be dropped
+        inner.images.retain(|src, state| {
+            finished.contains(src)
+                || match state {
+                    ImageState::Loading => true,
+                    ImageState::Loaded(image) => Arc::strong_count(image) > 1,
+                    ImageState::Failed(err) => Arc::strong_count(err) > 1,
+                }
+        });
+        drop(inner);
+        let mut changed = Vec::new();
+        if finished.is_empty() {
+            return changed;
+        }
+        rdom.traverse_depth_first(|n| {
+            if let Some(image) = &n.state.layout.image {
+                if finished.contains(&image.src) {
+                    changed.push((n.id, NodeMask::ALL));
+                }
+            }
+        });
+        changed
+    }
+}
+
+/// Read the bytes of a resource with the first loader that handles it.
+fn load(src: &str, loaders: &[Arc<dyn ResourceLoader>]) -> Result<Vec<u8>, String> {
+    if let Some(data) = src.strip_prefix("data:") {
+        return decode_data_url(data);
+    }
+    if let Some(result) = loaders.iter().find_map(|l| l.load(src)) {
+        return result;
+    }
+    let path = match scheme(src) {
+        None => src.to_string(),
+        Some("file") => file_path(src)?,
+        Some(scheme) => return Err(format!("no resource loader for {}: urls", scheme)),
+    };
+    std::fs::read(&path).map_err(|err| format!("failed to read {}: {}", path, err))
+}
+
+/// The path of a `file:` url, like `file:///a%20b.png`. Only local files can be read.
+fn file_path(url: &str) -> Result<String, String> {
+    let rest = &url["file:".len()..];
+    let path = match rest.strip_prefix("//") {
+        Some(rest) => {
+            let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+                return Err(format!("can't read files on {}: {}", host, url));
+            }
+            path
+        }
+        None => rest,
+    };
+    // the query and fragment are not part of the path
+    let path = &path[..path.find(|c| c == '?' || c == '#').unwrap_or(path.len())];
+    String::from_utf8(percent_decode(path)).map_err(|_| format!("invalid path in {}", url))
+}
+
+/// The scheme of a url, or `None` for a path. Windows drive letters are not schemes.
+fn scheme(src: &str) -> Option<&str> {
+    let (scheme, _) = src.split_once(':')?;
+    let valid = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then(|| scheme)
+}
+
+/// Decode the part of a `data:` url after the scheme, like `image/png;base64,iVBOR...`.
+fn decode_data_url(data: &str) -> Result<Vec<u8>, String> {
+    let (media_type, data) = data
+        .split_once(',')
+        .ok_or_else(|| "data url without a comma".to_string())?;
+    let data = percent_decode(data);
+    if media_type.ends_with(";base64") {
+        // base64 data may be wrapped over several lines
+        let data: Vec<u8> = data
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        base64::decode(data).map_err(|err| format!("invalid base64 in data url: {}", err))
+    } else {
+        Ok(data)
+    }
+}
+
+fn percent_decode(data: &str) -> Vec<u8> {
+    let bytes = data.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Decode a PNG, JPEG, GIF or WebP image. Only the first frame of an animation is kept.
+fn decode(bytes: &[u8]) -> Result<DecodedImage, String> {
+    let image = ::image::load_from_memory(bytes).map_err(|err| err.to_string())?;
+    let image = image.to_rgba8();
+    Ok(DecodedImage {
+        width: image.width(),
+        height: image.height(),
+        pixels: image.into_raw(),
+    })
+}
+
+/// The image last reported to every `img` element.
+#[derive(Default)]
+pub(crate) struct LoadObserver {
+    reported: FxHashMap<ElementId, String>,
+}
+
+impl LoadObserver {
+    /// Return a load or error event for every element whose image finished loading since the
+    /// last update.
+    pub(crate) fn update(&mut self, rdom: &Dom) -> Vec<UserEvent> {
+        let mut reported = FxHashMap::default();
+        let mut events = Vec::new();
+        rdom.traverse_depth_first(|n| {
+            let image = match &n.state.layout.image {
+                Some(image) => image,
+                None => return,
+            };
+            let name = match image.state {
+                ImageState::Loading => return,
+                ImageState::Loaded(_) => "load",
+                ImageState::Failed(_) => "error",
+            };
+            if self.reported.get(&n.id) != Some(&image.src) {
+                events.push(UserEvent {
+                    scope_id: None,
+                    priority: EventPriority::Medium,
+                    element: Some(n.id),
+                    name,
+                    data: Arc::new(ImageData {
+                        load_error: name == "error",
+                    }),
+                    bubbles: false,
+                });
+            }
+            reported.insert(n.id, image.src.clone());
+        });
+        self.reported = reported;
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_data_urls() {
+        assert_eq!(decode_data_url("text/plain,a%20b").unwrap(), b"a b");
+        assert_eq!(decode_data_url("image/png;base64,aG\nk=").unwrap(), b"hi");
+        assert!(decode_data_url("text/plain").is_err());
+    }
+
+    #[test]
+    fn reads_the_path_of_local_file_urls() {
+        assert_eq!(file_path("file:///a%20b.png").unwrap(), "/a b.png");
+        assert_eq!(file_path("file://localhost/a.png").unwrap(), "/a.png");
+        assert_eq!(file_path("file:a.png?v=1#top").unwrap(), "a.png");
+        assert!(file_path("file://host/a.png").is_err());
+    }
+}
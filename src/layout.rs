@@ -1,17 +1,92 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use dioxus::core::{Attribute, ElementId};
+use dioxus::core::ElementId;
 use dioxus_native_core::layout_attributes::apply_layout_attributes;
 use dioxus_native_core::node_ref::{AttributeMask, NodeMask};
+use dioxus_native_core::real_dom::NodeType;
 use dioxus_native_core::state::ChildDepState;
+use piet_wgpu::kurbo::{Point, Rect, Vec2};
 use taffy::prelude::*;
 
+use crate::image::{ImageLoader, ImageSource, ImageState};
+use crate::render::Damage;
+use crate::scroll::{clamp_scroll, max_scroll};
+use crate::shadow::ink_bounds;
+use crate::style::{all_declarations, Border, Font};
+use crate::stylesheet::Cascade;
+use crate::text::{Line, TextContext};
+use crate::{Dom, DomNode};
+
+/// The context shared by every node while computing the layout.
+#[derive(Clone)]
+pub(crate) struct LayoutContext {
+    pub stretch: Rc<RefCell<Taffy>>,
+    pub text: Rc<RefCell<TextContext>>,
+    pub cascade: Cascade,
+    pub images: ImageLoader,
+}
+
+impl LayoutContext {
+    /// Fails if the text context used to measure text can't be created.
+    pub(crate) fn new(cascade: Cascade, images: ImageLoader) -> Result<Self, piet_common::Error> {
+        Ok(Self {
+            stretch: Rc::new(RefCell::new(Taffy::new())),
+            text: Rc::new(RefCell::new(TextContext::new()?)),
+            cascade,
+            images,
+        })
+    }
+}
+
 #[derive(Clone, Default, Debug)]
-pub struct StretchLayout {
+pub(crate) struct StretchLayout {
     pub style: Style,
     pub node: Option<Node>,
     pub layout: Option<Layout>,
+    /// The text a text node was last measured with.
+    pub text: String,
+    /// The lines of a text node after wrapping it to its computed width.
+    pub lines: Vec<Line>,
+    /// The marker drawn next to a list item.
+    pub marker: Option<Line>,
+    /// The font a text node was last measured with.
+    pub font: Font,
+    /// Where the node is in the window after the last layout.
+    pub boxes: Boxes,
+    /// What the node paints over after the last layout.
+    pub ink: Ink,
+    /// How far the content of the node can be scrolled after the last layout.
+    pub max_scroll: Vec2,
+    /// The image an `img` element shows.
+    pub image: Option<ImageSource>,
+}
+
+/// The boxes of a node in window coordinates, including the scroll offsets of its ancestors.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub(crate) struct Boxes {
+    pub border: Rect,
+    pub padding: Rect,
+    pub content: Rect,
+}
+
+/// The areas a node paints over in window coordinates.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub(crate) struct Ink {
+    /// What the node paints itself: its border box and outer shadows, its list marker, or the
+    /// glyphs of its text, which may overflow its box.
+    pub own: Rect,
+    /// The ink of the node and of its descendants, where they aren't clipped away.
+    pub subtree: Rect,
+}
+
+/// What changed while the layout was computed or an element was scrolled.
+#[derive(Default)]
+pub(crate) struct LayoutChanges {
+    /// The area covered by nodes that moved or now paint differently.
+    pub damage: Damage,
+    /// The elements whose scroll offset changed, which are sent a `scroll` event.
+    pub scrolled: Vec<ElementId>,
 }
 
 impl PartialEq<Self> for StretchLayout {
@@ -21,10 +96,12 @@ impl PartialEq<Self> for StretchLayout {
 }
 
 impl ChildDepState for StretchLayout {
-    type Ctx = Rc<RefCell<Taffy>>;
+    type Ctx = LayoutContext;
     type DepState = Self;
 
-    const NODE_MASK: NodeMask = NodeMask::new_with_attrs(AttributeMask::All).with_text();
+    const NODE_MASK: NodeMask = NodeMask::new_with_attrs(AttributeMask::All)
+        .with_text()
+        .with_tag();
     /// Setup the layout
     fn reduce<'a>(
         &mut self,
@@ -35,28 +112,27 @@ impl ChildDepState for StretchLayout {
     where
         Self::DepState: 'a,
     {
-        let mut stretch = ctx.borrow_mut();
+        let mut stretch = ctx.stretch.borrow_mut();
         let mut changed = false;
         if let Some(text) = node.text() {
-            let char_len = text.chars().count();
-
-            // todo: this should change with the font
-            let style = Style {
-                size: Size {
-                    height: Dimension::Points(10.0),
-
-                    width: Dimension::Points(char_len as f32 * 10.0),
-                },
-                ..Default::default()
-            };
+            let style = Style::default();
 
+            // the font is inherited, so it is kept up to date in compute_layout
             if let Some(n) = self.node {
                 if self.style != style {
                     stretch.set_style(n, style).unwrap();
+                }
+                if self.text != text {
+                    stretch
+                        .set_measure(n, Some(text_measure(text, &self.font, ctx)))
+                        .unwrap();
+                    self.text = text.to_string();
                     changed = true;
                 }
             } else {
-                self.node = Some(stretch.new_node(style, &[]).unwrap());
+                let measure = text_measure(text, &self.font, ctx);
+                self.node = Some(stretch.new_leaf(style, measure).unwrap());
+                self.text = text.to_string();
                 changed = true;
             }
 
@@ -68,10 +144,32 @@ impl ChildDepState for StretchLayout {
             // gather up all the styles from the attribute list
             let mut style = Style::default();
 
-            for Attribute { name, value, .. } in node.attributes() {
-                if let Some(value) = value.as_text() {
-                    apply_layout_attributes(name, value, &mut style);
-                }
+            for (name, value) in all_declarations(&node, &ctx.cascade) {
+                apply_layout_attributes(&name, &value, &mut style);
+            }
+
+            // a side of the border only takes up space if it has a style. Border widths can't be
+            // percentages, but widths in viewport units are not supported in the layout yet.
+            let widths = Border::from_node(&node, &ctx.cascade).widths(
+                &Size {
+                    width: 0.0,
+                    height: 0.0,
+                },
+                &Size {
+                    width: 0,
+                    height: 0,
+                },
+            );
+            style.border = taffy::geometry::Rect {
+                start: Dimension::Points(widths.x0 as f32),
+                end: Dimension::Points(widths.x1 as f32),
+                top: Dimension::Points(widths.y0 as f32),
+                bottom: Dimension::Points(widths.y1 as f32),
+            };
+
+            // the contents of style elements are never displayed
+            if node.tag() == Some("style") {
+                style.display = Display::None;
             }
 
             // the root node fills the entire area
@@ -100,6 +198,25 @@ impl ChildDepState for StretchLayout {
                 changed = true;
             }
 
+            // images are sized like their content until they are loaded
+            let image = match node.tag() {
+                Some("img") => node
+                    .attributes()
+                    .find(|a| a.name == "src")
+                    .and_then(|a| a.value.as_text())
+                    .map(|src| ImageSource {
+                        src: src.to_string(),
+                        state: ctx.images.get(src),
+                    }),
+                _ => None,
+            };
+            if image != self.image {
+                let measure = image.as_ref().map(image_measure);
+                stretch.set_measure(self.node.unwrap(), measure).unwrap();
+                self.image = image;
+                changed = true;
+            }
+
             if style != self.style {
                 self.style = style;
                 changed = true;
@@ -108,3 +225,270 @@ impl ChildDepState for StretchLayout {
         changed
     }
 }
+
+/// Measure a text node with the width taffy makes available to it.
+fn text_measure(text: &str, font: &Font, ctx: &LayoutContext) -> MeasureFunc {
+    let text = text.to_string();
+    let font = font.clone();
+    let text_ctx = ctx.text.clone();
+    MeasureFunc::Boxed(Box::new(move |constraint: Size<Number>| {
+        let max_width = constraint.width.or_else(f32::INFINITY);
+        text_ctx
+            .borrow_mut()
+            .measure(&text, &font, max_width as f64)
+    }))
+}
+
+/// Size an image with its natural size, scaled to keep its aspect ratio if the style sets one
+/// of the sides. Images that aren't loaded take up no space.
+fn image_measure(image: &ImageSource) -> MeasureFunc {
+    let (width, height) = match &image.state {
+        ImageState::Loaded(image) => (image.width as f32, image.height as f32),
+        _ => (0.0, 0.0),
+    };
+    MeasureFunc::Boxed(Box::new(move |known: Size<Number>| {
+        match (known.width, known.height) {
+            (Number::Defined(w), Number::Defined(h)) => Size {
+                width: w,
+                height: h,
+            },
+            (Number::Defined(w), Number::Undefined) if width > 0.0 => Size {
+                width: w,
+                height: w * height / width,
+            },
+            (Number::Undefined, Number::Defined(h)) if height > 0.0 => Size {
+                width: h * width / height,
+                height: h,
+            },
+            _ => Size { width, height },
+        }
+    }))
+}
+
+/// Compute the layout of the whole tree and copy the results into each node's state.
+pub(crate) fn compute_layout(
+    rdom: &mut Dom,
+    ctx: &LayoutContext,
+    size: Size<Number>,
+) -> LayoutChanges {
+    let mut stretch = ctx.stretch.borrow_mut();
+    // text nodes inherit their font, which is only known after the style has been updated
+    rdom.traverse_depth_first_mut(|n| {
+        if let (NodeType::Text { text }, Some(node)) = (&n.node_type, n.state.layout.node) {
+            if n.state.layout.font != n.state.style.font {
+                n.state.layout.font = n.state.style.font.clone();
+                stretch
+                    .set_measure(node, Some(text_measure(text, &n.state.layout.font, ctx)))
+                    .unwrap();
+            }
+        }
+    });
+    stretch
+        .compute_layout(
+            rdom[ElementId(rdom.root_id())].state.layout.node.unwrap(),
+            size,
+        )
+        .unwrap();
+    // the measure functions borrow the text context while the layout is computed
+    let mut text_ctx = ctx.text.borrow_mut();
+    rdom.traverse_depth_first_mut(|n| {
+        if let Some(node) = n.state.layout.node {
+            let layout = *stretch.layout(node).unwrap();
+            // wrap the text the same way it was measured so rendering matches the layout
+            if let NodeType::Text { text } = &n.node_type {
+                n.state.layout.lines =
+                    text_ctx.wrap(text, &n.state.style.font, layout.size.width as f64);
+            }
+            n.state.layout.layout = Some(layout);
+        }
+    });
+    // list items are numbered by their position among the list items of their parent
+    let mut items = Vec::new();
+    rdom.traverse_depth_first(|n| {
+        if let NodeType::Element { children, .. } = &n.node_type {
+            let list_items = children.iter().filter(
+                |c| matches!(&rdom[**c].node_type, NodeType::Element { tag, .. } if tag == "li"),
+            );
+            items.extend(list_items.enumerate().map(|(index, id)| (*id, index)));
+        }
+    });
+    for (id, index) in items {
+        let style = &rdom[id].state.style;
+        let marker = style
+            .list_style
+            .marker(index)
+            .map(|marker| text_ctx.line(&marker, &style.font));
+        rdom[id].state.layout.marker = marker;
+    }
+    drop(text_ctx);
+    drop(stretch);
+
+    let viewport_size = Size {
+        width: size.width.or_else(0.0) as u32,
+        height: size.height.or_else(0.0) as u32,
+    };
+    let root_id = ElementId(rdom.root_id());
+    let mut changes = LayoutChanges::default();
+    set_boxes(
+        rdom,
+        root_id,
+        Point::ZERO,
+        viewport_size.width as f64,
+        &viewport_size,
+        &mut changes,
+    );
+    update_child_boxes(rdom, root_id, &viewport_size, &mut changes);
+    changes
+}
+
+/// Compute the boxes of all the descendants of a node from the node's own boxes.
+///
+/// This has to run again for a scroll container's descendants whenever it is scrolled.
+pub(crate) fn update_child_boxes(
+    rdom: &mut Dom,
+    id: ElementId,
+    viewport_size: &Size<u32>,
+    changes: &mut LayoutChanges,
+) {
+    let node = &rdom[id];
+    let children = match &node.node_type {
+        NodeType::Element { children, .. } => children.clone(),
+        _ => return,
+    };
+    // the content of a scroll container may have shrunk since it was scrolled
+    let max = max_scroll(rdom, node);
+    let scroll = clamp_scroll(rdom, node);
+    let state = &mut rdom[id].state;
+    if scroll != state.scroll || max != state.layout.max_scroll {
+        // the scrollbars are drawn over the element
+        changes.damage.add(state.layout.ink.own);
+    }
+    if scroll != state.scroll {
+        changes.scrolled.push(id);
+    }
+    state.scroll = scroll;
+    state.layout.max_scroll = max;
+    let boxes = state.layout.boxes;
+    let origin = boxes.border.origin() - scroll;
+    // percentages in the padding refer to the width of the containing block
+    let containing_width = boxes.content.width();
+    let clip = state.style.overflow.clips().then(|| boxes.padding);
+    let mut ink = state.layout.ink.own;
+    for child in children {
+        set_boxes(
+            rdom,
+            child,
+            origin,
+            containing_width,
+            viewport_size,
+            changes,
+        );
+        update_child_boxes(rdom, child, viewport_size, changes);
+        let child_ink = rdom[child].state.layout.ink.subtree;
+        ink = union(
+            ink,
+            clip.map_or(child_ink, |clip| child_ink.intersect(clip)),
+        );
+    }
+    rdom[id].state.layout.ink.subtree = ink;
+}
+
+/// Compute the boxes of a node whose parent's border box starts at `origin`.
+fn set_boxes(
+    rdom: &mut Dom,
+    id: ElementId,
+    origin: Point,
+    containing_width: f64,
+    viewport_size: &Size<u32>,
+    changes: &mut LayoutChanges,
+) {
+    let state = &mut rdom[id].state;
+    let layout = match state.layout.layout {
+        Some(layout) => layout,
+        None => return,
+    };
+    let border = Rect::from_origin_size(
+        origin + Vec2::new(layout.location.x as f64, layout.location.y as f64),
+        (layout.size.width as f64, layout.size.height as f64),
+    );
+
+    let padding = border - state.style.border.widths(&layout.size, viewport_size);
+
+    let style_padding = &state.layout.style.padding;
+    let padding_width = |dimension: Dimension| match dimension {
+        Dimension::Points(points) => points as f64,
+        Dimension::Percent(percent) => percent as f64 * containing_width,
+        _ => 0.0,
+    };
+    let content = Rect::new(
+        padding.x0 + padding_width(style_padding.start),
+        padding.y0 + padding_width(style_padding.top),
+        padding.x1 - padding_width(style_padding.end),
+        padding.y1 - padding_width(style_padding.bottom),
+    );
+
+    state.layout.boxes = Boxes {
+        border,
+        padding,
+        content,
+    };
+
+    let ink = own_ink(&rdom[id], viewport_size);
+    let old = &mut rdom[id].state.layout.ink;
+    if ink != old.own {
+        changes.damage.add(old.own);
+        changes.damage.add(ink);
+    }
+    *old = Ink {
+        own: ink,
+        subtree: ink,
+    };
+}
+
+/// The area a node paints over itself, from its boxes and lines.
+fn own_ink(node: &DomNode, viewport_size: &Size<u32>) -> Rect {
+    let style = &node.state.style;
+    let layout = &node.state.layout;
+    let border = layout.boxes.border;
+    // glyphs can reach outside of their line box
+    let overhang = style.font.size / 4.0;
+    match &node.node_type {
+        NodeType::Text { .. } => {
+            let mut glyphs = border;
+            let mut y = border.y0;
+            for line in &layout.lines {
+                let x = border.x0 + style.text_align.offset(line.width, border.width());
+                let height = style.font.line_box_height(line.height);
+                let line = Rect::new(x, y, x + line.width, y + height);
+                glyphs = union(glyphs, line.inflate(overhang, overhang));
+                y += height;
+            }
+            ink_bounds(node, viewport_size, glyphs)
+        }
+        NodeType::Element { .. } => {
+            let ink = ink_bounds(node, viewport_size, border);
+            match &layout.marker {
+                Some(marker) => {
+                    // the marker is drawn left of the first line, see render_list_marker
+                    let x = border.x0 - marker.width - style.font.size / 4.0;
+                    let height = style.font.line_box_height(marker.height);
+                    let marker = Rect::new(x, border.y0, x + marker.width, border.y0 + height);
+                    union(ink, marker.inflate(overhang, overhang))
+                }
+                None => ink,
+            }
+        }
+        _ => Rect::ZERO,
+    }
+}
+
+/// The union of two areas, where an empty area doesn't add anything.
+pub(crate) fn union(a: Rect, b: Rect) -> Rect {
+    if b.area() <= 0.0 {
+        a
+    } else if a.area() <= 0.0 {
+        b
+    } else {
+        a.union(b)
+    }
+}
@@ -1,42 +1,191 @@
 use crate::node::BlitzNodeState;
-use application::ApplicationState;
+use dioxus::core::ElementId;
 use dioxus::prelude::*;
 use dioxus_native_core::real_dom::{Node, RealDom};
+use std::path::Path;
+use std::sync::Arc;
 
 use tao::{
+    dpi::{Position, Size},
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Fullscreen, WindowBuilder},
 };
 
 mod application;
+mod border;
+mod cache;
 mod events;
 mod focus;
+mod gradient;
+mod headless;
+mod image;
 mod layout;
 mod mouse;
 mod node;
+mod query;
 mod render;
+mod resize;
+mod scroll;
+mod shadow;
 mod style;
+mod stylesheet;
+mod text;
 mod util;
 
+pub use application::ApplicationState;
+pub use headless::HeadlessApp;
+pub use image::ResourceLoader;
+pub use query::{use_element_rect, ElementRect, Rect};
+pub use resize::ResizeData;
+pub use tao::dpi;
+pub use tao::window::Icon as WindowIcon;
+
 type Dom = RealDom<BlitzNodeState>;
 type DomNode = Node<BlitzNodeState>;
-type TaoEvent<'a> = Event<'a, Redraw>;
+
+/// The root node holds the document but isn't part of it.
+fn is_document_node(id: ElementId) -> bool {
+    id != ElementId(0)
+}
 
 #[derive(Debug)]
 pub struct Redraw;
 
-#[derive(Default)]
-pub struct Config;
+pub struct Config {
+    default_stylesheet: Option<String>,
+    stylesheets: Vec<String>,
+    window: WindowBuilder,
+    resource_loaders: Vec<Arc<dyn ResourceLoader>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_stylesheet: Some(stylesheet::DEFAULT_STYLESHEET.to_string()),
+            stylesheets: Vec::new(),
+            window: WindowBuilder::new().with_title("Blitz"),
+            resource_loaders: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Replace the default styles for html elements, like the margins of a `p` or the font size
+    /// of a `h1`. Every other stylesheet takes precedence over these styles.
+    pub fn with_default_stylesheet(mut self, css: impl Into<String>) -> Self {
+        self.default_stylesheet = Some(css.into());
+        self
+    }
+
+    /// Don't apply any default styles to html elements.
+    pub fn without_default_stylesheet(mut self) -> Self {
+        self.default_stylesheet = None;
+        self
+    }
+
+    /// Add a stylesheet that applies to the whole app.
+    pub fn with_stylesheet(mut self, css: impl Into<String>) -> Self {
+        self.stylesheets.push(css.into());
+        self
+    }
+
+    /// Read a stylesheet that applies to the whole app from a file.
+    pub fn with_stylesheet_file(self, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let css = std::fs::read_to_string(path)?;
+        Ok(self.with_stylesheet(css))
+    }
+
+    /// Load resources like the images of `img` elements with a custom loader. Loaders are tried
+    /// in the order they were added, before falling back to reading a file.
+    pub fn with_resource_loader(mut self, loader: impl ResourceLoader + 'static) -> Self {
+        self.resource_loaders.push(Arc::new(loader));
+        self
+    }
+
+    /// Set the title of the window.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.window = self.window.with_title(title);
+        self
+    }
+
+    /// Set the size of the area inside the window's decorations when it is opened.
+    pub fn with_inner_size(mut self, size: impl Into<Size>) -> Self {
+        self.window = self.window.with_inner_size(size);
+        self
+    }
+
+    /// Stop the user from making the window smaller than a size.
+    pub fn with_min_inner_size(mut self, size: impl Into<Size>) -> Self {
+        self.window = self.window.with_min_inner_size(size);
+        self
+    }
+
+    /// Stop the user from making the window larger than a size.
+    pub fn with_max_inner_size(mut self, size: impl Into<Size>) -> Self {
+        self.window = self.window.with_max_inner_size(size);
+        self
+    }
+
+    /// Set where the window is opened on the desktop.
+    pub fn with_position(mut self, position: impl Into<Position>) -> Self {
+        self.window = self.window.with_position(position);
+        self
+    }
+
+    /// Set if the user can resize the window. Windows are resizable by default.
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.window = self.window.with_resizable(resizable);
+        self
+    }
+
+    /// Set if the window has a title bar and borders. Windows are decorated by default.
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.window = self.window.with_decorations(decorations);
+        self
+    }
+
+    /// Set if the parts of the window the dom doesn't paint show what is behind the window.
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.window = self.window.with_transparent(transparent);
+        self
+    }
+
+    /// Keep the window above all other windows.
+    pub fn with_always_on_top(mut self, always_on_top: bool) -> Self {
+        self.window = self.window.with_always_on_top(always_on_top);
+        self
+    }
+
+    /// Open the window fullscreen on the current monitor.
+    pub fn with_fullscreen(mut self, fullscreen: bool) -> Self {
+        self.window = self
+            .window
+            .with_fullscreen(fullscreen.then(|| Fullscreen::Borderless(None)));
+        self
+    }
+
+    /// Set the icon shown in the title bar and task bar.
+    pub fn with_window_icon(mut self, icon: WindowIcon) -> Self {
+        self.window = self.window.with_window_icon(Some(icon));
+        self
+    }
+}
 
 pub fn launch(root: Component<()>) {
     launch_cfg(root, Config::default())
 }
 
-pub fn launch_cfg(root: Component<()>, _cfg: Config) {
+pub fn launch_cfg(root: Component<()>, cfg: Config) {
     let event_loop = EventLoop::with_user_event();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
-    let mut appliction = ApplicationState::new(root, &window, event_loop.create_proxy());
+    let window = cfg.window.clone().build(&event_loop).unwrap();
+    let proxy = event_loop.create_proxy();
+    let mut appliction = ApplicationState::new(
+        root,
+        &window,
+        move || proxy.send_event(Redraw).unwrap(),
+        &cfg,
+    );
     appliction.render();
 
     event_loop.run(move |event, _, control_flow| {
@@ -45,7 +194,7 @@ pub fn launch_cfg(root: Component<()>, _cfg: Config) {
         // input, and uses significantly less power/CPU time than ControlFlow::Poll.
         *control_flow = ControlFlow::Wait;
 
-        appliction.send_event(&event);
+        appliction.handle_event(&event);
 
         match event {
             Event::WindowEvent {
@@ -60,7 +209,9 @@ pub fn launch_cfg(root: Component<()>, _cfg: Config) {
                 // You only need to call this if you've determined that you need to redraw, in
                 // applications which do not always need to. Applications that redraw continuously
                 // can just render here instead.
-                window.request_redraw();
+                if appliction.needs_redraw() {
+                    window.request_redraw();
+                }
             }
             Event::RedrawRequested(_) => {
                 // Redraw the application.
@@ -69,20 +220,11 @@ pub fn launch_cfg(root: Component<()>, _cfg: Config) {
                 // this event rather than in MainEventsCleared, since rendering in here allows
                 // the program to gracefully handle redraws requested by the OS.
 
-                if !appliction.clean().is_empty() {
-                    appliction.render();
-                }
+                appliction.render();
             }
             Event::UserEvent(_redraw) => {
                 window.request_redraw();
             }
-            Event::WindowEvent {
-                event: WindowEvent::Resized(physical_size),
-                window_id: _,
-                ..
-            } => {
-                appliction.set_size(physical_size);
-            }
             _ => (),
         }
     });
@@ -1,73 +1,170 @@
 use dioxus::core::ElementId;
-use dioxus_native_core::{
-    node_ref::{NodeMask, NodeView},
-    state::NodeDepState,
-};
-use dioxus_native_core_macro::sorted_str_slice;
-use piet_wgpu::kurbo::{Point, Shape};
+use dioxus_native_core::real_dom::NodeType;
+use fxhash::FxHashMap;
+use piet_wgpu::kurbo::{Point, Rect, Shape};
 use taffy::prelude::Size;
 
 use crate::{
-    render::{get_abs_pos, get_shape},
-    Dom, DomNode,
+    render::{get_shape, padding_box},
+    style::PointerEvents,
+    Dom,
 };
 
-pub(crate) fn get_hovered(
-    dom: &Dom,
-    viewport_size: &Size<u32>,
-    mouse_pos: Point,
-) -> Option<ElementId> {
-    let mut hovered: Option<ElementId> = None;
-    dom.traverse_depth_first(|node| {
-        if node.state.mouse_effected.0 && check_hovered(dom, node, viewport_size, mouse_pos) {
-            if let Some(id) = hovered {
-                if node.height > dom[id].height {
-                    hovered = Some(node.id);
-                }
-            } else {
-                hovered = Some(node.id);
-            }
-        }
-    });
-    hovered
+/// The size of the cells in the hit-test grid.
+const CELL_SIZE: f64 = 64.0;
+
+/// An element that can be hit.
+struct HitTarget {
+    id: ElementId,
+    /// The border box clipped by the ancestors' padding boxes.
+    bounds: Rect,
+    /// The innermost ancestor that clips the element, as an index into the clips.
+    clip: Option<usize>,
+}
+
+/// An element that clips its descendants, linked to the next ancestor that clips them.
+struct Clip {
+    id: ElementId,
+    parent: Option<usize>,
 }
 
-pub(crate) fn check_hovered(
-    dom: &Dom,
-    node: &DomNode,
-    viewport_size: &Size<u32>,
-    mouse_pos: Point,
-) -> bool {
-    get_shape(node, viewport_size, get_abs_pos(node, dom)).contains(mouse_pos)
+/// A grid over the window that finds the element under a point without visiting the whole dom.
+pub(crate) struct HitTestIndex {
+    targets: Vec<HitTarget>,
+    clips: Vec<Clip>,
+    cells: FxHashMap<(i32, i32), Vec<usize>>,
+    stale: bool,
 }
 
-#[derive(Debug, Default, PartialEq, Clone)]
-pub(crate) struct MouseEffected(bool);
+impl Default for HitTestIndex {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            clips: Vec::new(),
+            cells: FxHashMap::default(),
+            stale: true,
+        }
+    }
+}
 
-impl NodeDepState<()> for MouseEffected {
-    type Ctx = ();
+impl HitTestIndex {
+    /// Rebuild the index the next time it is used.
+    pub(crate) fn invalidate(&mut self) {
+        self.stale = true;
+    }
 
-    const NODE_MASK: NodeMask = NodeMask::new().with_listeners();
+    /// Find the topmost element under the point.
+    pub(crate) fn hit(
+        &mut self,
+        dom: &Dom,
+        viewport_size: &Size<u32>,
+        point: Point,
+    ) -> Option<ElementId> {
+        if self.stale {
+            self.rebuild(dom, viewport_size);
+        }
+        let cell = self.cells.get(&cell_of(point))?;
+        // elements painted later are on top
+        cell.iter()
+            .rev()
+            .map(|i| &self.targets[*i])
+            .find_map(|target| {
+                (target.bounds.contains(point)
+                    && get_shape(&dom[target.id], viewport_size).contains(point)
+                    && self.inside_clips(dom, target.clip, viewport_size, point))
+                .then(|| target.id)
+            })
+    }
 
-    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), _: &Self::Ctx) -> bool {
-        let new = Self(
-            node.listeners()
-                .iter()
-                .any(|l| MOUSE_EVENTS.binary_search(&l.event).is_ok()),
+    /// Check that a point is inside the rounded padding boxes of a chain of clipping elements.
+    /// The bounds of the targets only account for the rectangles of the padding boxes.
+    fn inside_clips(
+        &self,
+        dom: &Dom,
+        mut clip: Option<usize>,
+        viewport_size: &Size<u32>,
+        point: Point,
+    ) -> bool {
+        while let Some(index) = clip {
+            let Clip { id, parent } = self.clips[index];
+            if !padding_box(&dom[id], viewport_size).contains(point) {
+                return false;
+            }
+            clip = parent;
+        }
+        true
+    }
+
+    fn rebuild(&mut self, dom: &Dom, viewport_size: &Size<u32>) {
+        self.targets.clear();
+        self.clips.clear();
+        self.cells.clear();
+        let window = Rect::new(
+            0.0,
+            0.0,
+            viewport_size.width as f64,
+            viewport_size.height as f64,
         );
-        if *self != new {
-            *self = new;
-            true
+        self.add(dom, ElementId(1), window, None, viewport_size);
+        self.stale = false;
+    }
+
+    /// Add an element and its children in the order they are painted.
+    fn add(
+        &mut self,
+        dom: &Dom,
+        id: ElementId,
+        clip: Rect,
+        clips: Option<usize>,
+        viewport_size: &Size<u32>,
+    ) {
+        let node = &dom[id];
+        let children = match &node.node_type {
+            NodeType::Element { children, .. } => children,
+            _ => return,
+        };
+        if node.state.layout.layout.is_none() {
+            return;
+        }
+        let boxes = &node.state.layout.boxes;
+        let bounds = boxes.border.intersect(clip);
+
+        let style = &node.state.style;
+        if style.pointer_events == PointerEvents::Auto && bounds.area() > 0.0 {
+            let index = self.targets.len();
+            self.targets.push(HitTarget {
+                id,
+                bounds,
+                clip: clips,
+            });
+            let (x0, y0) = cell_of(Point::new(bounds.x0, bounds.y0));
+            let (x1, y1) = cell_of(Point::new(bounds.x1, bounds.y1));
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    self.cells.entry((x, y)).or_default().push(index);
+                }
+            }
+        }
+
+        let (clip, clips) = if style.overflow.clips() {
+            self.clips.push(Clip { id, parent: clips });
+            (clip.intersect(boxes.padding), Some(self.clips.len() - 1))
         } else {
-            false
+            (clip, clips)
+        };
+        // nothing inside an element that clips everything can be hit
+        if clip.area() <= 0.0 {
+            return;
+        }
+        for child in children {
+            self.add(dom, *child, clip, clips, viewport_size);
         }
     }
 }
 
-const MOUSE_EVENTS: &[&str] = &sorted_str_slice!([
-    "hover",
-    "mouseleave",
-    "mouseenter",
-    "mouseclick",
-    "mouseover"
-]);
+fn cell_of(point: Point) -> (i32, i32) {
+    (
+        (point.x / CELL_SIZE).floor() as i32,
+        (point.y / CELL_SIZE).floor() as i32,
+    )
+}
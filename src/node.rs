@@ -2,24 +2,30 @@ use dioxus::core as dioxus_core;
 use dioxus_native_core;
 use dioxus_native_core::state::*;
 use dioxus_native_core_macro::State;
+use piet_wgpu::kurbo::Vec2;
 
-use crate::layout::StretchLayout;
-use crate::mouse;
+use crate::layout::{LayoutContext, StretchLayout};
 use dioxus_native_core_macro::sorted_str_slice;
 
 #[derive(Clone, PartialEq, Default, State, Debug)]
 pub(crate) struct BlitzNodeState {
-    #[node_dep_state()]
-    pub(crate) mouse_effected: mouse::MouseEffected,
-    #[child_dep_state(layout, Rc<RefCell<Stretch>>)]
+    #[child_dep_state(layout, LayoutContext)]
     pub layout: StretchLayout,
     #[state]
     pub style: crate::style::Style,
     #[node_dep_state()]
     pub focus: crate::focus::Focus,
     pub focused: bool,
+    /// How far the content of a scroll container is scrolled.
+    pub scroll: Vec2,
     #[node_dep_state()]
     pub prevent_default: PreventDefault,
+    #[node_dep_state()]
+    pub resize: crate::resize::ResizeListener,
+    #[node_dep_state()]
+    pub listeners: crate::events::Listeners,
+    #[node_dep_state()]
+    pub stop_propagation: StopPropagation,
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -92,3 +98,44 @@ impl NodeDepState<()> for PreventDefault {
         }
     }
 }
+
+/// The event that doesn't reach the ancestors of an element after its own listeners ran.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub(crate) struct StopPropagation(Option<String>);
+
+impl StopPropagation {
+    pub(crate) fn stops(&self, event: &str) -> bool {
+        self.0.as_deref().and_then(|e| e.strip_prefix("on")) == Some(event)
+    }
+}
+
+impl NodeDepState<()> for StopPropagation {
+    type Ctx = ();
+
+    const NODE_MASK: dioxus_native_core::node_ref::NodeMask =
+        dioxus_native_core::node_ref::NodeMask::new_with_attrs(
+            dioxus_native_core::node_ref::AttributeMask::Static(&sorted_str_slice!([
+                "dioxus-stop-propagation"
+            ])),
+        );
+
+    fn reduce(
+        &mut self,
+        node: dioxus_native_core::node_ref::NodeView,
+        _sibling: (),
+        _ctx: &Self::Ctx,
+    ) -> bool {
+        let new = StopPropagation(
+            node.attributes()
+                .find(|a| a.name == "dioxus-stop-propagation")
+                .and_then(|a| a.value.as_text())
+                .map(str::to_string),
+        );
+        if new == *self {
+            false
+        } else {
+            *self = new;
+            true
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use dioxus::core::{ElementId, SchedulerMsg, ScopeId};
+use dioxus::prelude::{ScopeState, UnboundedSender, VirtualDom};
+use fxhash::FxHashMap;
+use piet_wgpu::kurbo;
+
+use crate::Dom;
+
+/// A rectangle in window pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+
+    fn from_kurbo(rect: kurbo::Rect) -> Self {
+        Self {
+            x: rect.x0,
+            y: rect.y0,
+            width: rect.width(),
+            height: rect.height(),
+        }
+    }
+}
+
+/// The boxes of an element in window coordinates, after layout and scrolling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElementRect {
+    /// The area inside the margins, including the borders.
+    pub border: Rect,
+    /// The area inside the borders.
+    pub padding: Rect,
+    /// The area inside the padding, where the children are laid out.
+    pub content: Rect,
+}
+
+/// Get the boxes of the root element of the current component, or `None` before it is laid
+/// out. The component is rendered again whenever the boxes change.
+pub fn use_element_rect(cx: &ScopeState) -> Option<ElementRect> {
+    let observed = cx.use_hook(|_| {
+        cx.consume_context::<RectObserver>().map(|observer| {
+            observer.observe(cx.scope_id());
+            Observed {
+                observer,
+                scope: cx.scope_id(),
+            }
+        })
+    });
+    observed
+        .as_ref()
+        .and_then(|observed| observed.observer.rect(observed.scope))
+}
+
+/// Stops observing a component when its hooks are dropped.
+struct Observed {
+    observer: RectObserver,
+    scope: ScopeId,
+}
+
+impl Drop for Observed {
+    fn drop(&mut self) {
+        self.observer.unobserve(self.scope);
+    }
+}
+
+/// The boxes last seen by every component that uses [`use_element_rect`].
+#[derive(Clone)]
+pub(crate) struct RectObserver {
+    rects: Rc<RefCell<FxHashMap<ScopeId, Option<ElementRect>>>>,
+    scheduler: UnboundedSender<SchedulerMsg>,
+}
+
+impl RectObserver {
+    /// Make an observer available to every component in the vdom before it is built.
+    pub(crate) fn provide(vdom: &VirtualDom) -> Self {
+        let observer = Self {
+            rects: Rc::default(),
+            scheduler: vdom.get_scheduler_channel(),
+        };
+        vdom.base_scope().provide_context(observer.clone());
+        observer
+    }
+
+    fn observe(&self, scope: ScopeId) {
+        self.rects.borrow_mut().insert(scope, None);
+    }
+
+    fn unobserve(&self, scope: ScopeId) {
+        self.rects.borrow_mut().remove(&scope);
+    }
+
+    fn rect(&self, scope: ScopeId) -> Option<ElementRect> {
+        self.rects.borrow().get(&scope).copied().flatten()
+    }
+
+    /// Read the boxes of every observed element and render the components whose boxes changed.
+    pub(crate) fn update(&self, rdom: &Dom, vdom: &VirtualDom) {
+        for (scope, last) in self.rects.borrow_mut().iter_mut() {
+            let rect = vdom
+                .get_scope(*scope)
+                .and_then(|scope| scope.root_node().try_mounted_id())
+                .and_then(|id| element_rect(rdom, id));
+            if rect != *last {
+                *last = rect;
+                // the channel is closed once the vdom is dropped, which has nothing to rerender
+                let _ = self
+                    .scheduler
+                    .unbounded_send(SchedulerMsg::Immediate(*scope));
+            }
+        }
+    }
+}
+
+fn element_rect(rdom: &Dom, id: ElementId) -> Option<ElementRect> {
+    let layout = &rdom[id].state.layout;
+    layout.layout.map(|_| ElementRect {
+        border: Rect::from_kurbo(layout.boxes.border),
+        padding: Rect::from_kurbo(layout.boxes.padding),
+        content: Rect::from_kurbo(layout.boxes.content),
+    })
+}
@@ -1,33 +1,48 @@
-use dioxus::core::ElementId;
+use std::sync::Arc;
+
+use dioxus::core::{DomEdit, ElementId, Mutations};
 use dioxus_native_core::real_dom::NodeType;
-use piet_wgpu::kurbo::{Point, Rect, RoundedRect, Vec2};
-use piet_wgpu::{Color, Piet, RenderContext, Text, TextLayoutBuilder};
+use parcel_css::properties::overflow::OverflowKeyword;
+use piet_common::{BitmapTarget, Device, ImageFormat, PietImage};
+use piet_wgpu::kurbo::{BezPath, Insets, Point, Rect, RoundedRect};
+use piet_wgpu::{Color, InterpolationMode, RenderContext};
 use taffy::prelude::Size;
 use tao::dpi::PhysicalSize;
 
-use crate::util::{translate_color, Axis, Resolve};
+use crate::border::{insets_between, render_border, BoxShape};
+use crate::cache::RenderCache;
+use crate::gradient::render_background_images;
+use crate::image::{DecodedImage, ImageSource, ImageState};
+use crate::scroll::SCROLLBAR_WIDTH;
+use crate::shadow::{render_inset_box_shadows, render_outer_box_shadows, render_text_shadows};
+use crate::style::{BackgroundClip, FitKeyword, Visibility};
+use crate::util::{resolve_color, resolve_position, translate_color};
 use crate::{Dom, DomNode};
 
 const FOCUS_BORDER_WIDTH: f64 = 6.0;
 
-pub(crate) fn render(dom: &Dom, piet: &mut Piet, window_size: PhysicalSize<u32>) {
-    let root = &dom[ElementId(1)];
-    let root_layout = root.state.layout.layout.unwrap();
-    let background_brush = piet.solid_brush(Color::WHITE);
-    piet.fill(
-        &Rect {
-            x0: root_layout.location.x.into(),
-            y0: root_layout.location.y.into(),
-            x1: (root_layout.location.x + root_layout.size.width).into(),
-            y1: (root_layout.location.y + root_layout.size.height).into(),
-        },
-        &background_brush,
-    );
+/// Paint the parts of the dom that overlap a region and leave the rest of the previous frame.
+///
+/// The region is cleared to the background color first.
+pub(crate) fn render_region<P: RenderContext>(
+    dom: &Dom,
+    piet: &mut P,
+    cache: &mut RenderCache<P::Image>,
+    window_size: PhysicalSize<u32>,
+    region: Rect,
+    background: Color,
+) {
+    piet.clear(region, background);
+    piet.save().unwrap();
+    piet.clip(region);
     let viewport_size = Size {
         width: window_size.width,
         height: window_size.height,
     };
-    render_node(dom, root, piet, Point::ZERO, &viewport_size);
+    let root = &dom[ElementId(1)];
+    render_node(dom, root, piet, cache, &viewport_size, region);
+    piet.restore().unwrap();
+    cache.end_frame();
     match piet.finish() {
         Ok(()) => {}
         Err(e) => {
@@ -36,151 +51,399 @@ pub(crate) fn render(dom: &Dom, piet: &mut Piet, window_size: PhysicalSize<u32>)
     }
 }
 
-fn render_node(
+/// The area of a frame that has to be painted again, collected from the nodes that changed.
+#[derive(Default)]
+pub(crate) struct Damage(Option<Rect>);
+
+impl Damage {
+    pub(crate) fn add(&mut self, rect: Rect) {
+        if rect.area() > 0.0 {
+            self.0 = Some(self.0.map_or(rect, |damage| damage.union(rect)));
+        }
+    }
+
+    pub(crate) fn extend(&mut self, other: Damage) {
+        if let Some(rect) = other.0 {
+            self.add(rect);
+        }
+    }
+
+    /// Add the area of a node that is painted differently, e.g. because its style changed.
+    pub(crate) fn add_node(&mut self, dom: &Dom, id: ElementId) {
+        if let Some(node) = dom.get(id.0) {
+            self.add(node.state.layout.ink.own);
+        }
+    }
+
+    /// Add the areas of the nodes the mutations remove. Call this before applying them.
+    pub(crate) fn add_removed(&mut self, dom: &Dom, mutations: &[Mutations]) {
+        for edit in mutations.iter().flat_map(|m| &m.edits) {
+            let root = match edit {
+                DomEdit::Remove { root } | DomEdit::ReplaceWith { root, .. } => *root,
+                _ => continue,
+            };
+            // nodes created by earlier mutations in the same batch were never painted
+            if let Some(node) = dom.get(root as usize) {
+                self.add(node.state.layout.ink.subtree);
+            }
+        }
+    }
+
+    /// Take the damaged area, rounded out to whole pixels so anti-aliased edges are repainted
+    /// too. Returns `None` if nothing in the window changed.
+    pub(crate) fn take(&mut self, window: Rect) -> Option<Rect> {
+        self.0
+            .take()
+            .map(|damage| damage.expand().intersect(window))
+            .filter(|damage| damage.area() > 0.0)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+/// A frame painted on the CPU that is kept between frames, so only the damaged area of it is
+/// painted again.
+pub(crate) struct RetainedFrame {
+    device: Device,
+    cache: RenderCache<PietImage>,
+    /// The last frame as premultiplied RGBA pixels and the size it was painted at.
+    last: Option<(PhysicalSize<u32>, Vec<u8>)>,
+}
+
+impl RetainedFrame {
+    pub(crate) fn new() -> Result<Self, piet_common::Error> {
+        Ok(Self {
+            device: Device::new()?,
+            cache: RenderCache::new()?,
+            last: None,
+        })
+    }
+
+    /// Paint the damaged area of the dom over the last frame, or the whole frame if its size
+    /// changed. Returns the target the frame was painted into.
+    pub(crate) fn paint(
+        &mut self,
+        dom: &Dom,
+        size: PhysicalSize<u32>,
+        damage: &mut Damage,
+        background: Color,
+    ) -> Result<BitmapTarget<'_>, piet_common::Error> {
+        let (width, height) = (size.width as usize, size.height as usize);
+        let window = Rect::new(0.0, 0.0, width as f64, height as f64);
+        let damage = damage.take(window);
+        let mut target = self.device.bitmap_target(width, height, 1.0)?;
+        {
+            let mut piet = target.render_context();
+            let region = match &self.last {
+                Some((last_size, pixels)) if *last_size == size => {
+                    let image = piet.make_image(width, height, pixels, ImageFormat::RgbaPremul)?;
+                    piet.draw_image(&image, window, InterpolationMode::NearestNeighbor);
+                    damage
+                }
+                _ => Some(window),
+            };
+            match region {
+                Some(region) => {
+                    render_region(dom, &mut piet, &mut self.cache, size, region, background)
+                }
+                None => piet.finish()?,
+            }
+        }
+        let mut pixels = vec![0; width * height * 4];
+        target.copy_raw_pixels(ImageFormat::RgbaPremul, &mut pixels)?;
+        self.last = Some((size, pixels));
+        Ok(target)
+    }
+
+    /// The last frame as premultiplied RGBA pixels, row by row.
+    pub(crate) fn pixels(&self) -> &[u8] {
+        self.last.as_ref().map_or(&[], |(_, pixels)| pixels)
+    }
+}
+
+fn render_node<P: RenderContext>(
     dom: &Dom,
     node: &DomNode,
-    piet: &mut Piet,
-    location: Point,
+    piet: &mut P,
+    cache: &mut RenderCache<P::Image>,
     viewport_size: &Size<u32>,
+    region: Rect,
 ) {
     let style = &node.state.style;
     let layout = node.state.layout.layout.unwrap();
-    let pos = location + Vec2::new(layout.location.x as f64, layout.location.y as f64);
-    match &node.node_type {
-        NodeType::Text { text } => {
-            let text_layout = piet
-                .text()
-                .new_text_layout(text.clone())
-                .text_color(translate_color(&style.color.0))
-                .build()
-                .unwrap();
-            piet.draw_text(&text_layout, pos);
+    let pos = node.state.layout.boxes.border.origin();
+    let ink = &node.state.layout.ink;
+    if ink.subtree.intersect(region).area() <= 0.0 {
+        return;
+    }
+    if ink.own.intersect(region).area() <= 0.0 {
+        // the children can overflow the node
+        if let NodeType::Element { children, .. } = &node.node_type {
+            render_children(dom, node, children, piet, cache, viewport_size, region);
         }
-        NodeType::Element { children, .. } => {
-            let shape = get_shape(node, viewport_size, pos);
-            let fill_brush = piet.solid_brush(translate_color(&style.bg_color.0));
-            if node.state.focused {
-                let stroke_brush = piet.solid_brush(Color::rgb(1.0, 1.0, 1.0));
-                piet.stroke(&shape, &stroke_brush, FOCUS_BORDER_WIDTH / 2.0);
-                let mut smaller_rect = shape.rect();
-                smaller_rect.x0 += FOCUS_BORDER_WIDTH / 2.0;
-                smaller_rect.x1 -= FOCUS_BORDER_WIDTH / 2.0;
-                smaller_rect.y0 += FOCUS_BORDER_WIDTH / 2.0;
-                smaller_rect.y1 -= FOCUS_BORDER_WIDTH / 2.0;
-                let smaller_shape = RoundedRect::from_rect(smaller_rect, shape.radii());
-                let stroke_brush = piet.solid_brush(Color::rgb(0.0, 0.0, 0.0));
-                piet.stroke(&smaller_shape, &stroke_brush, FOCUS_BORDER_WIDTH / 2.0);
-                piet.fill(&smaller_shape, &fill_brush);
-            } else {
-                let stroke_brush = piet.solid_brush(translate_color(&style.border.colors.top));
-                piet.stroke(
-                    &shape,
-                    &stroke_brush,
-                    style.border.width.top.resolve(
-                        Axis::Min,
-                        &node.state.layout.layout.unwrap().size,
-                        viewport_size,
-                    ),
+        return;
+    }
+    match &node.node_type {
+        NodeType::Text { .. } => {
+            if style.visibility == Visibility::Hidden {
+                return;
+            }
+            let color = translate_color(&style.color.0);
+            let mut line_y = pos.y;
+            for line in &node.state.layout.lines {
+                let line_height = style.font.line_box_height(line.height);
+                // center the glyphs in the line box like the half-leading in CSS
+                let line_pos = Point::new(
+                    pos.x
+                        + style
+                            .text_align
+                            .offset(line.width, layout.size.width as f64),
+                    line_y + (line_height - line.height) / 2.0,
                 );
-                piet.fill(&shape, &fill_brush);
+                render_text_shadows(node, &line.text, piet, cache, line_pos, viewport_size);
+                cache
+                    .text(piet, &line.text, &style.font, &color, 0.0)
+                    .draw(piet, line_pos);
+                line_y += line_height;
+            }
+        }
+        NodeType::Element { tag, children, .. } if tag != "style" => {
+            if style.visibility == Visibility::Visible {
+                render_list_marker(node, piet, cache, pos);
+            }
+            // hidden elements still paint their children, which may be visible
+            if style.visibility == Visibility::Hidden {
+                render_children(dom, node, children, piet, cache, viewport_size, region);
+                return;
+            }
+            let shape = BoxShape::new(node, viewport_size);
+            let boxes = &node.state.layout.boxes;
+            let background = match style.background.clip {
+                BackgroundClip::BorderBox => shape.border_box(),
+                BackgroundClip::PaddingBox => {
+                    shape.inset(insets_between(boxes.border, boxes.padding))
+                }
+                BackgroundClip::ContentBox => {
+                    shape.inset(insets_between(boxes.border, boxes.content))
+                }
             };
+            render_outer_box_shadows(node, piet, viewport_size);
+            let fill_brush =
+                piet.solid_brush(resolve_color(&style.background.color, &style.color.0));
+            piet.fill(background.clone(), &fill_brush);
+            render_background_images(node, piet, &background, viewport_size);
+            render_inset_box_shadows(node, piet, viewport_size);
+            render_border(node, piet, viewport_size);
+            if let Some(ImageSource {
+                state: ImageState::Loaded(image),
+                ..
+            }) = &node.state.layout.image
+            {
+                render_image(node, image, piet, cache, &shape, viewport_size);
+            }
+            if node.state.focused {
+                // a white ring with a black ring inside it shows up on any background
+                let half = FOCUS_BORDER_WIDTH / 2.0;
+                let mut outer = shape.border_box();
+                outer.extend(shape.inset(Insets::uniform(half)));
+                let brush = piet.solid_brush(Color::WHITE);
+                piet.fill_even_odd(outer, &brush);
+                let mut inner = shape.inset(Insets::uniform(half));
+                inner.extend(shape.inset(Insets::uniform(FOCUS_BORDER_WIDTH)));
+                let brush = piet.solid_brush(Color::BLACK);
+                piet.fill_even_odd(inner, &brush);
+            }
 
-            for child in children {
-                render_node(dom, &dom[*child], piet, pos, viewport_size);
+            render_children(dom, node, children, piet, cache, viewport_size, region);
+            if style.overflow.clips() {
+                render_scrollbars(node, piet);
             }
         }
         _ => {}
     }
 }
 
-pub(crate) fn get_shape(node: &DomNode, viewport_size: &Size<u32>, location: Point) -> RoundedRect {
-    let layout = node.state.layout.layout.unwrap();
-    let style = &node.state.style;
-
-    let axis = Axis::Min;
-    let rect = layout.size;
-    let x: f64 = location.x;
-    let y: f64 = location.y;
-    let width: f64 = layout.size.width.into();
-    let height: f64 = layout.size.height.into();
-    let left_border_width = if node.state.focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        style.border.width.left.resolve(axis, &rect, viewport_size)
-    };
-    let right_border_width = if node.state.focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        style.border.width.right.resolve(axis, &rect, viewport_size)
+/// Paint the image of an `img` element in its content box, sized and placed by `object-fit` and
+/// `object-position`.
+fn render_image<P: RenderContext>(
+    node: &DomNode,
+    image: &Arc<DecodedImage>,
+    piet: &mut P,
+    cache: &mut RenderCache<P::Image>,
+    shape: &BoxShape,
+    viewport_size: &Size<u32>,
+) {
+    let boxes = &node.state.layout.boxes;
+    let object_fit = &node.state.style.object_fit;
+    let (width, height) = (boxes.content.width(), boxes.content.height());
+    let (natural_width, natural_height) = (image.width as f64, image.height as f64);
+    if natural_width <= 0.0 || natural_height <= 0.0 {
+        return;
+    }
+    let contain = (width / natural_width).min(height / natural_height);
+    let scale = match object_fit.fit {
+        FitKeyword::Fill => None,
+        FitKeyword::Contain => Some(contain),
+        FitKeyword::Cover => Some((width / natural_width).max(height / natural_height)),
+        FitKeyword::None => Some(1.0),
+        FitKeyword::ScaleDown => Some(contain.min(1.0)),
     };
-    let top_border_width = if node.state.focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        style.border.width.top.resolve(axis, &rect, viewport_size)
+    let size = scale.map_or((width, height), |s| (natural_width * s, natural_height * s));
+    // percentages in the position refer to the space left around the image
+    let free = Size {
+        width: (width - size.0) as f32,
+        height: (height - size.1) as f32,
     };
-    let bottom_border_width = if node.state.focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        style
-            .border
-            .width
-            .bottom
-            .resolve(axis, &rect, viewport_size)
+    let offset = resolve_position(&object_fit.position, &free, viewport_size);
+    let dest = Rect::from_origin_size(boxes.content.origin() + offset, size);
+
+    let bitmap = match cache.image(piet, image) {
+        Some(bitmap) => bitmap,
+        None => return,
     };
+    piet.save().unwrap();
+    piet.clip(shape.inset(insets_between(boxes.border, boxes.content)));
+    piet.draw_image(bitmap, dest, InterpolationMode::Bilinear);
+    piet.restore().unwrap();
+}
 
-    // The stroke is drawn on the outside of the border, so we need to offset the rect by the border width for each side.
-    let x_start = x + left_border_width / 2.0;
-    let y_start = y + top_border_width / 2.0;
-    let x_end = x + width - right_border_width / 2.0;
-    let y_end = y + height - bottom_border_width / 2.0;
-
-    RoundedRect::new(
-        x_start,
-        y_start,
-        x_end,
-        y_end,
-        (
-            style
-                .border
-                .radius
-                .top_left
-                .0
-                .resolve(axis, &rect, viewport_size),
-            style
-                .border
-                .radius
-                .top_right
-                .0
-                .resolve(axis, &rect, viewport_size),
-            style
-                .border
-                .radius
-                .bottom_right
-                .0
-                .resolve(axis, &rect, viewport_size),
-            style
-                .border
-                .radius
-                .bottom_left
-                .0
-                .resolve(axis, &rect, viewport_size),
-        ),
-    )
+/// Paint the children of an element, clipped to its padding box and moved by its scroll offset
+/// if its content overflows.
+fn render_children<P: RenderContext>(
+    dom: &Dom,
+    node: &DomNode,
+    children: &[ElementId],
+    piet: &mut P,
+    cache: &mut RenderCache<P::Image>,
+    viewport_size: &Size<u32>,
+    region: Rect,
+) {
+    let clips = node.state.style.overflow.clips();
+    if clips {
+        piet.save().unwrap();
+        piet.clip(padding_box(node, viewport_size));
+    }
+    for child in children {
+        render_node(dom, &dom[*child], piet, cache, viewport_size, region);
+    }
+    if clips {
+        piet.restore().unwrap();
+    }
 }
 
-pub(crate) fn get_abs_pos(node: &DomNode, dom: &Dom) -> Point {
-    let mut node_layout = node.state.layout.layout.unwrap().location;
-    let mut current = node;
-    while let Some(parent_id) = current.parent {
-        // the root element is positioned at (0, 0)
-        if parent_id == ElementId(0) {
-            break;
+/// Draw scrollbars along the right and bottom edges of the padding box of a scroll container.
+fn render_scrollbars(node: &DomNode, piet: &mut impl RenderContext) {
+    let overflow = &node.state.style.overflow;
+    let padding = node.state.layout.boxes.padding;
+    let max = node.state.layout.max_scroll;
+    let scroll = node.state.scroll;
+    let track_brush = piet.solid_brush(Color::rgba8(0, 0, 0, 20));
+    let thumb_brush = piet.solid_brush(Color::rgba8(0, 0, 0, 100));
+
+    // auto only shows a scrollbar if there is something to scroll
+    let show_y =
+        matches!(overflow.y, OverflowKeyword::Scroll) || (overflow.scrolls_y() && max.y > 0.0);
+    let show_x =
+        matches!(overflow.x, OverflowKeyword::Scroll) || (overflow.scrolls_x() && max.x > 0.0);
+
+    if show_y {
+        let bottom = if show_x {
+            padding.y1 - SCROLLBAR_WIDTH
+        } else {
+            padding.y1
+        };
+        let track = Rect::new(padding.x1 - SCROLLBAR_WIDTH, padding.y0, padding.x1, bottom);
+        piet.fill(track, &track_brush);
+        if max.y > 0.0 {
+            let visible = padding.height();
+            let length = (track.height() * visible / (visible + max.y)).max(SCROLLBAR_WIDTH);
+            let start = track.y0 + (track.height() - length) * scroll.y / max.y;
+            let thumb = Rect::new(track.x0, start, track.x1, start + length);
+            piet.fill(
+                RoundedRect::from_rect(thumb, SCROLLBAR_WIDTH / 2.0),
+                &thumb_brush,
+            );
+        }
+    }
+    if show_x {
+        let right = if show_y {
+            padding.x1 - SCROLLBAR_WIDTH
+        } else {
+            padding.x1
+        };
+        let track = Rect::new(padding.x0, padding.y1 - SCROLLBAR_WIDTH, right, padding.y1);
+        piet.fill(track, &track_brush);
+        if max.x > 0.0 {
+            let visible = padding.width();
+            let length = (track.width() * visible / (visible + max.x)).max(SCROLLBAR_WIDTH);
+            let start = track.x0 + (track.width() - length) * scroll.x / max.x;
+            let thumb = Rect::new(start, track.y0, start + length, track.y1);
+            piet.fill(
+                RoundedRect::from_rect(thumb, SCROLLBAR_WIDTH / 2.0),
+                &thumb_brush,
+            );
         }
-        let parent = &dom[parent_id];
-        current = parent;
-        let parent_layout = parent.state.layout.layout.unwrap();
-        node_layout.x += parent_layout.location.x;
-        node_layout.y += parent_layout.location.y;
     }
-    Point::new(node_layout.x as f64, node_layout.y as f64)
+}
+
+/// Draw the marker of a list item to the left of its first line.
+fn render_list_marker<P: RenderContext>(
+    node: &DomNode,
+    piet: &mut P,
+    cache: &mut RenderCache<P::Image>,
+    pos: Point,
+) {
+    let style = &node.state.style;
+    if let Some(marker) = &node.state.layout.marker {
+        let color = translate_color(&style.color.0);
+        let line_height = style.font.line_box_height(marker.height);
+        // leave a gap the size of a space between the marker and the content
+        let gap = style.font.size / 4.0;
+        cache
+            .text(piet, &marker.text, &style.font, &color, 0.0)
+            .draw(
+                piet,
+                Point::new(
+                    pos.x - marker.width - gap,
+                    pos.y + (line_height - marker.height) / 2.0,
+                ),
+            );
+    }
+}
+
+/// The outline of the border box of an element, with its corners rounded.
+pub(crate) fn get_shape(node: &DomNode, viewport_size: &Size<u32>) -> BezPath {
+    BoxShape::new(node, viewport_size).border_box()
+}
+
+/// The area inside the borders of an element, where its children are clipped to.
+pub(crate) fn padding_box(node: &DomNode, viewport_size: &Size<u32>) -> BezPath {
+    let boxes = &node.state.layout.boxes;
+    BoxShape::new(node, viewport_size).inset(insets_between(boxes.border, boxes.padding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_the_damage_rounded_out_to_the_window() {
+        let window = Rect::new(0.0, 0.0, 40.0, 30.0);
+        let mut damage = Damage::default();
+        assert!(damage.is_empty());
+        damage.add(Rect::new(1.5, 2.0, 4.0, 4.2));
+        // empty rects don't damage anything
+        damage.add(Rect::new(10.0, 10.0, 10.0, 20.0));
+        let mut other = Damage::default();
+        other.add(Rect::new(30.0, 5.0, 50.0, 6.0));
+        damage.extend(other);
+        assert_eq!(damage.take(window), Some(Rect::new(1.0, 2.0, 40.0, 6.0)));
+        assert!(damage.is_empty());
+        assert_eq!(damage.take(window), None);
+
+        damage.add(Rect::new(50.0, 50.0, 60.0, 60.0));
+        assert_eq!(damage.take(window), None);
+    }
 }
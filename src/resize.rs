@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use dioxus::core::{ElementId, EventPriority, UserEvent};
+use dioxus_native_core::node_ref::{AttributeMask, NodeMask, NodeView};
+use dioxus_native_core::state::NodeDepState;
+use fxhash::FxHashMap;
+use piet_wgpu::kurbo::Size;
+
+use crate::Dom;
+
+/// The data of a `resize` event: the new size of the element's content box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResizeData {
+    pub width: f64,
+    pub height: f64,
+}
+
+/// If an element listens for resize events.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct ResizeListener(pub bool);
+
+impl NodeDepState<()> for ResizeListener {
+    type Ctx = ();
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(&[])).with_listeners();
+
+    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), _: &Self::Ctx) -> bool {
+        let new = ResizeListener(node.listeners().iter().any(|l| l.event == "resize"));
+        if *self != new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The content box sizes last sent to every element with a resize listener.
+#[derive(Default)]
+pub(crate) struct ResizeObserver {
+    sizes: FxHashMap<ElementId, Size>,
+}
+
+impl ResizeObserver {
+    /// Return a resize event for every listening element whose size changed since the last
+    /// update.
+    pub(crate) fn update(&mut self, rdom: &Dom) -> Vec<UserEvent> {
+        let mut sizes = FxHashMap::default();
+        rdom.traverse_depth_first(|n| {
+            if n.state.resize.0 && n.state.layout.layout.is_some() {
+                sizes.insert(n.id, n.state.layout.boxes.content.size());
+            }
+        });
+        let old = std::mem::replace(&mut self.sizes, sizes);
+
+        self.sizes
+            .iter()
+            .filter(|(id, size)| old.get(id) != Some(size))
+            .map(|(id, size)| UserEvent {
+                scope_id: None,
+                priority: EventPriority::Medium,
+                element: Some(*id),
+                name: "resize",
+                data: Arc::new(ResizeData {
+                    width: size.width,
+                    height: size.height,
+                }),
+                bubbles: false,
+            })
+            .collect()
+    }
+}
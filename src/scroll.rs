@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use dioxus::core::{ElementId, EventPriority, UserEvent};
+use dioxus::events::ScrollData;
+use dioxus_native_core::real_dom::NodeType;
+use piet_wgpu::kurbo::Vec2;
+use taffy::prelude::Size;
+
+use crate::layout::{update_child_boxes, LayoutChanges};
+use crate::{Dom, DomNode};
+
+/// The number of pixels scrolled for each line a mouse wheel reports.
+pub(crate) const PIXELS_PER_LINE: f64 = 40.0;
+
+/// The thickness of the scrollbars drawn over scroll containers.
+pub(crate) const SCROLLBAR_WIDTH: f64 = 8.0;
+
+/// How far the content of a node can be scrolled on each axis.
+///
+/// The content is scrolled until the far edge of the last child lines up with the far edge of
+/// the content box, so the end padding still shows after it like in a browser.
+pub(crate) fn max_scroll(dom: &Dom, node: &DomNode) -> Vec2 {
+    let mut content = Vec2::ZERO;
+    if let NodeType::Element { children, .. } = &node.node_type {
+        for child in children {
+            if let Some(layout) = dom[*child].state.layout.layout {
+                content.x = content
+                    .x
+                    .max((layout.location.x + layout.size.width) as f64);
+                content.y = content
+                    .y
+                    .max((layout.location.y + layout.size.height) as f64);
+            }
+        }
+    }
+    // the children are laid out relative to the border box
+    let boxes = &node.state.layout.boxes;
+    Vec2::new(
+        (content.x - (boxes.content.x1 - boxes.border.x0)).max(0.0),
+        (content.y - (boxes.content.y1 - boxes.border.y0)).max(0.0),
+    )
+}
+
+/// Keep the scroll offset of a node in the range its content allows on the axes it can scroll.
+pub(crate) fn clamp_scroll(dom: &Dom, node: &DomNode) -> Vec2 {
+    let overflow = &node.state.style.overflow;
+    let max = max_scroll(dom, node);
+    let scroll = node.state.scroll;
+    Vec2::new(
+        if overflow.scrolls_x() {
+            scroll.x.clamp(0.0, max.x)
+        } else {
+            0.0
+        },
+        if overflow.scrolls_y() {
+            scroll.y.clamp(0.0, max.y)
+        } else {
+            0.0
+        },
+    )
+}
+
+/// The event sent to an element after its scroll offset changed. It doesn't bubble.
+pub(crate) fn scroll_event(id: ElementId) -> UserEvent {
+    UserEvent {
+        scope_id: None,
+        priority: EventPriority::Medium,
+        element: Some(id),
+        name: "scroll",
+        data: Arc::new(ScrollData {}),
+        bubbles: false,
+    }
+}
+
+/// Scroll the innermost container around the target that can still move in the direction of
+/// the delta and return it.
+pub(crate) fn scroll_by(
+    dom: &mut Dom,
+    viewport_size: &Size<u32>,
+    target: ElementId,
+    delta: Vec2,
+    changes: &mut LayoutChanges,
+) -> Option<ElementId> {
+    let mut current = Some(target);
+    while let Some(id) = current {
+        let overflow = &dom[id].state.style.overflow;
+        if overflow.scrolls_x() || overflow.scrolls_y() {
+            let old = dom[id].state.scroll;
+            dom[id].state.scroll = old + delta;
+            let new = clamp_scroll(dom, &dom[id]);
+            dom[id].state.scroll = new;
+            if new != old {
+                // the scrollbars are drawn over the element
+                changes.damage.add(dom[id].state.layout.ink.own);
+                changes.scrolled.push(id);
+                update_child_boxes(dom, id, viewport_size, changes);
+                return Some(id);
+            }
+        }
+        current = dom[id].parent;
+    }
+    None
+}
@@ -0,0 +1,215 @@
+use dioxus_native_core::real_dom::NodeType;
+use parcel_css::values::color::CssColor;
+use parcel_css::values::length::Length;
+use piet_wgpu::kurbo::{Affine, BezPath, Insets, Point, Rect, Vec2};
+use piet_wgpu::{Color, RenderContext};
+use taffy::prelude::Size;
+
+use crate::border::{insets_between, BoxShape};
+use crate::cache::RenderCache;
+use crate::util::{resolve_color, Axis, Resolve};
+use crate::DomNode;
+
+/// How many layers a blurred box shadow is drawn with.
+const BLUR_LAYERS: usize = 8;
+
+/// A shadow with its lengths and color resolved.
+struct Shadow {
+    offset: Vec2,
+    blur: f64,
+    spread: f64,
+    color: Color,
+    inset: bool,
+}
+
+impl Shadow {
+    fn resolve(
+        node: &DomNode,
+        viewport_size: &Size<u32>,
+        lengths: [&Length; 4],
+        color: &CssColor,
+        inset: bool,
+    ) -> Self {
+        let size = node.state.layout.layout.unwrap().size;
+        let [x, y, blur, spread] = lengths;
+        Self {
+            offset: Vec2::new(
+                x.resolve(Axis::X, &size, viewport_size),
+                y.resolve(Axis::Y, &size, viewport_size),
+            ),
+            // negative blur radii are invalid in css
+            blur: blur.resolve(Axis::Min, &size, viewport_size).max(0.0),
+            spread: spread.resolve(Axis::Min, &size, viewport_size),
+            color: resolve_color(color, &node.state.style.color.0),
+            inset,
+        }
+    }
+}
+
+fn box_shadows<'a>(
+    node: &'a DomNode,
+    viewport_size: &'a Size<u32>,
+) -> impl DoubleEndedIterator<Item = Shadow> + 'a {
+    node.state.style.box_shadow.0.iter().map(|s| {
+        Shadow::resolve(
+            node,
+            viewport_size,
+            [&s.x_offset, &s.y_offset, &s.blur, &s.spread],
+            &s.color,
+            s.inset,
+        )
+    })
+}
+
+fn text_shadows<'a>(
+    node: &'a DomNode,
+    viewport_size: &'a Size<u32>,
+) -> impl DoubleEndedIterator<Item = Shadow> + 'a {
+    node.state.style.text_shadow.0.iter().map(|s| {
+        Shadow::resolve(
+            node,
+            viewport_size,
+            [&s.x_offset, &s.y_offset, &s.blur, &s.spread],
+            &s.color,
+            false,
+        )
+    })
+}
+
+/// The area covered by `base` and the outer shadows it casts, where `base` is the border box of
+/// an element or the glyphs of a text node.
+pub(crate) fn ink_bounds(node: &DomNode, viewport_size: &Size<u32>, base: Rect) -> Rect {
+    let shadow_bounds = |s: &Shadow, extent: f64| (base + s.offset).inflate(extent, extent);
+    match &node.node_type {
+        // the blur of text fades out after 1.5 times the blur radius, see TextContext::rasterize
+        NodeType::Text { .. } => text_shadows(node, viewport_size).fold(base, |bounds, s| {
+            bounds.union(shadow_bounds(&s, 1.5 * s.blur))
+        }),
+        NodeType::Element { .. } => {
+            box_shadows(node, viewport_size)
+                .filter(|s| !s.inset)
+                .fold(base, |bounds, s| {
+                    let extent = (s.spread + s.blur).max(0.0);
+                    bounds.union(shadow_bounds(&s, extent))
+                })
+        }
+        _ => base,
+    }
+}
+
+/// Paint the outer shadows of an element. They are painted before the background and only
+/// show outside of the border box.
+pub(crate) fn render_outer_box_shadows(
+    node: &DomNode,
+    piet: &mut impl RenderContext,
+    viewport_size: &Size<u32>,
+) {
+    if box_shadows(node, viewport_size).all(|s| s.inset) {
+        return;
+    }
+    let shape = BoxShape::new(node, viewport_size);
+    let bounds = node.state.layout.ink.own.inflate(1.0, 1.0);
+    // the first shadow is on top
+    for shadow in box_shadows(node, viewport_size).rev().filter(|s| !s.inset) {
+        piet.save().unwrap();
+        piet.clip(outside(shape.border_box(), bounds));
+        piet.transform(Affine::translate(shadow.offset));
+        paint_blurred(piet, &shadow.color, shadow.blur, |offset| {
+            shape.inset(Insets::uniform(-(shadow.spread + offset)))
+        });
+        piet.restore().unwrap();
+    }
+}
+
+/// Paint the inset shadows of an element. They are painted above the background and below the
+/// border, inside the padding box.
+pub(crate) fn render_inset_box_shadows(
+    node: &DomNode,
+    piet: &mut impl RenderContext,
+    viewport_size: &Size<u32>,
+) {
+    if !box_shadows(node, viewport_size).any(|s| s.inset) {
+        return;
+    }
+    let shape = BoxShape::new(node, viewport_size);
+    let boxes = &node.state.layout.boxes;
+    let widths = insets_between(boxes.border, boxes.padding);
+    for shadow in box_shadows(node, viewport_size).rev().filter(|s| s.inset) {
+        // the shadow is cast by the edge of the padding box, so a hole that moves with the
+        // offset and shrinks with the spread is cut out of it
+        let bounds = (boxes.padding - shadow.offset).inflate(shadow.blur + 1.0, shadow.blur + 1.0);
+        piet.save().unwrap();
+        piet.clip(shape.inset(widths));
+        piet.transform(Affine::translate(shadow.offset));
+        paint_blurred(piet, &shadow.color, shadow.blur, |offset| {
+            let inset = shadow.spread + offset;
+            let hole = shape.inset(Insets::new(
+                widths.x0 + inset,
+                widths.y0 + inset,
+                widths.x1 + inset,
+                widths.y1 + inset,
+            ));
+            outside(hole, bounds)
+        });
+        piet.restore().unwrap();
+    }
+}
+
+/// Paint the shadows of a line of text at the position the text is drawn at.
+///
+/// Blurred shadows are rasterized with the blur applied, like the text itself.
+pub(crate) fn render_text_shadows<P: RenderContext>(
+    node: &DomNode,
+    text: &str,
+    piet: &mut P,
+    cache: &mut RenderCache<P::Image>,
+    pos: Point,
+    viewport_size: &Size<u32>,
+) {
+    let font = &node.state.style.font;
+    // the first shadow is on top
+    for shadow in text_shadows(node, viewport_size).rev() {
+        cache
+            .text(piet, text, font, &shadow.color, shadow.blur)
+            .draw(piet, pos + shadow.offset);
+    }
+}
+
+/// Approximate a gaussian blur of a shape by stacking translucent copies of it, grown by
+/// `shape_at` between `blur` and `-blur`.
+fn paint_blurred(
+    piet: &mut impl RenderContext,
+    color: &Color,
+    blur: f64,
+    shape_at: impl Fn(f64) -> BezPath,
+) {
+    if blur <= 0.0 {
+        let brush = piet.solid_brush(color.clone());
+        piet.fill(shape_at(0.0), &brush);
+        return;
+    }
+    let (r, g, b, a) = color.as_rgba();
+    let mut covered = 0.0;
+    for i in 0..BLUR_LAYERS {
+        let offset = blur * (1.0 - (2 * i + 1) as f64 / BLUR_LAYERS as f64);
+        let coverage = a * (i + 1) as f64 / BLUR_LAYERS as f64;
+        let alpha = (coverage - covered) / (1.0 - covered);
+        covered = coverage;
+        let brush = piet.solid_brush(Color::rgba(r, g, b, alpha));
+        piet.fill(shape_at(offset), &brush);
+    }
+}
+
+/// A path covering everything in `bounds` except the inside of `shape`.
+fn outside(shape: BezPath, bounds: Rect) -> BezPath {
+    // the rectangle winds the other way around, so the shape cuts a hole in it with the
+    // nonzero fill rule that clipping uses
+    let mut path = BezPath::new();
+    path.move_to((bounds.x0, bounds.y0));
+    path.line_to((bounds.x0, bounds.y1));
+    path.line_to((bounds.x1, bounds.y1));
+    path.line_to((bounds.x1, bounds.y0));
+    path.close_path();
+    path.extend(shape);
+    path
+}
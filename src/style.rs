@@ -5,88 +5,270 @@ use dioxus_native_core::node_ref::{AttributeMask, NodeMask, NodeView};
 use dioxus_native_core::state::NodeDepState;
 use dioxus_native_core::state::{ParentDepState, State};
 use dioxus_native_core_macro::{sorted_str_slice, State};
+
+use crate::stylesheet::{parse_declarations, Cascade, Declaration, Origin};
+use crate::text::DEFAULT_FONT_SIZE;
+use crate::util::{Axis, Resolve};
+use cssparser::RGBA;
+use parcel_css::properties::background;
 use parcel_css::properties::border::BorderColor;
 use parcel_css::properties::border::BorderSideWidth;
+use parcel_css::properties::border::BorderStyle;
 use parcel_css::properties::border::BorderWidth;
+use parcel_css::properties::border::LineStyle;
 use parcel_css::properties::border_radius::BorderRadius;
+use parcel_css::properties::box_shadow::BoxShadow;
+use parcel_css::properties::font::{
+    AbsoluteFontSize, AbsoluteFontWeight, FontFamily, FontSize, FontStyle, FontWeight,
+    GenericFontFamily, LineHeight as CssLineHeight, RelativeFontSize,
+};
+use parcel_css::properties::overflow::OverflowKeyword;
+use parcel_css::properties::text::TextShadow;
 use parcel_css::traits::Parse;
 use parcel_css::values::color::CssColor;
+use parcel_css::values::image::Image;
+use parcel_css::values::length::LengthValue;
+use parcel_css::values::percentage::DimensionPercentage;
+use parcel_css::values::position::{Position, PositionComponent};
 use parcel_css::{properties::Property, stylesheet::ParserOptions};
+use piet_wgpu::kurbo::Insets;
+use taffy::prelude::Size;
+use tao::window::CursorIcon;
 
 #[derive(Clone, PartialEq, Debug, State)]
 pub(crate) struct Style {
-    #[parent_dep_state(color)]
+    #[parent_dep_state(color, Cascade)]
     pub color: ForgroundColor,
-    #[node_dep_state()]
-    pub bg_color: BackgroundColor,
-    #[node_dep_state()]
+    #[node_dep_state(NONE, Cascade)]
+    pub background: Background,
+    #[node_dep_state(NONE, Cascade)]
     pub border: Border,
+    #[node_dep_state(NONE, Cascade)]
+    pub overflow: Overflow,
+    #[node_dep_state(NONE, Cascade)]
+    pub box_shadow: BoxShadows,
+    #[node_dep_state(NONE, Cascade)]
+    pub object_fit: ObjectFit,
+    #[parent_dep_state(font, Cascade)]
+    pub font: Font,
+    #[parent_dep_state(text_align, Cascade)]
+    pub text_align: TextAlign,
+    #[parent_dep_state(visibility, Cascade)]
+    pub visibility: Visibility,
+    #[parent_dep_state(cursor, Cascade)]
+    pub cursor: Cursor,
+    #[parent_dep_state(list_style, Cascade)]
+    pub list_style: ListStyleType,
+    #[parent_dep_state(pointer_events, Cascade)]
+    pub pointer_events: PointerEvents,
+    #[parent_dep_state(text_shadow, Cascade)]
+    pub text_shadow: TextShadows,
 }
 
 impl Default for Style {
     fn default() -> Self {
-        use cssparser::RGBA;
         Style {
-            color: ForgroundColor(CssColor::RGBA(RGBA::new(0, 0, 0, 255))),
-            bg_color: BackgroundColor(CssColor::RGBA(RGBA::new(255, 255, 255, 0))),
+            color: ForgroundColor::default(),
+            background: Background::default(),
             border: Border::default(),
+            overflow: Overflow::default(),
+            box_shadow: BoxShadows::default(),
+            object_fit: ObjectFit::default(),
+            font: Font::default(),
+            text_align: TextAlign::default(),
+            visibility: Visibility::default(),
+            cursor: Cursor::default(),
+            list_style: ListStyleType::default(),
+            pointer_events: PointerEvents::default(),
+            text_shadow: TextShadows::default(),
         }
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
-pub(crate) struct BackgroundColor(pub CssColor);
-impl NodeDepState<()> for BackgroundColor {
-    type Ctx = ();
+/// Collect the declarations of the given properties that apply to a node, from lowest to highest
+/// precedence.
+pub(crate) fn declarations(
+    node: &NodeView<'_>,
+    cascade: &Cascade,
+    properties: &[&str],
+) -> Vec<(String, String)> {
+    all_declarations(node, cascade)
+        .into_iter()
+        .filter(|(name, _)| properties.contains(&name.as_str()))
+        .collect()
+}
 
-    const NODE_MASK: NodeMask =
-        NodeMask::new_with_attrs(AttributeMask::Static(&["background-color"]));
-
-    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), _: &Self::Ctx) -> bool {
-        if let Some(color_attr) = node.attributes().next() {
-            if let Some(as_text) = color_attr.value.as_text() {
-                let mut value = ParserInput::new(as_text);
-                let mut parser = Parser::new(&mut value);
-                if let Ok(new_color) = CssColor::parse(&mut parser) {
-                    if self.0 != new_color {
-                        *self = Self(new_color);
-                        return true;
-                    }
-                }
+/// Collect all the declarations that apply to a node, from lowest to highest precedence.
+///
+/// The `style` attribute and the attributes for single properties act like an inline style:
+/// they override the normal declarations from stylesheets, but not the `!important` ones.
+/// Attributes for single properties are more specific than the `style` attribute, so they win
+/// over it unless it marks a declaration as `!important`. Like in CSS, `!important` declarations
+/// from the default stylesheet win over all others.
+pub(crate) fn all_declarations(node: &NodeView<'_>, cascade: &Cascade) -> Vec<(String, String)> {
+    let mut inline = Vec::new();
+    let mut style_attribute = Vec::new();
+    for a in node.attributes() {
+        if let Some(value) = a.value.as_text() {
+            if a.name == "style" {
+                style_attribute = parse_declarations(value);
+            } else {
+                inline.push((a.name.to_string(), value.to_string()));
             }
         }
+    }
+    by_precedence(cascade.declarations(node.id()), style_attribute, inline)
+}
+
+/// Order the declarations from the stylesheets, the `style` attribute and the attributes for
+/// single properties from lowest to highest precedence.
+pub(crate) fn by_precedence(
+    matched: Vec<(Origin, Declaration)>,
+    style_attribute: Vec<Declaration>,
+    inline: Vec<(String, String)>,
+) -> Vec<(String, String)> {
+    let mut normal = Vec::new();
+    let mut important = Vec::new();
+    let mut user_agent_important = Vec::new();
+    for (origin, declaration) in matched {
+        match (declaration.important, origin) {
+            (false, _) => normal.push(declaration),
+            (true, Origin::UserAgent) => user_agent_important.push(declaration),
+            (true, Origin::Author) => important.push(declaration),
+        }
+    }
+    let (style_important, style_normal): (Vec<_>, Vec<_>) =
+        style_attribute.into_iter().partition(|d| d.important);
+
+    normal
+        .into_iter()
+        .chain(style_normal)
+        .map(|d| (d.name, d.value))
+        .chain(inline)
+        .chain(
+            important
+                .into_iter()
+                .chain(style_important)
+                .chain(user_agent_important)
+                .map(|d| (d.name, d.value)),
+        )
+        .collect()
+}
+
+/// A state computed from properties that are inherited, like `color` or `font-size`.
+pub(crate) trait Inherited: Clone + PartialEq + Default {
+    /// The properties and attributes the state is computed from.
+    const PROPERTIES: &'static [&'static str];
+
+    /// Apply a declaration of one of the properties on top of the current value.
+    fn apply(&mut self, name: &str, value: &str, parent: &Self);
+
+    /// Copy the value of one property from another state.
+    fn copy_property(&mut self, name: &str, from: &Self);
+}
+
+/// Compute an inherited state from the parent's state and the declarations on the node.
+pub(crate) fn reduce_inherited<T: Inherited>(
+    state: &mut T,
+    node: &NodeView<'_>,
+    parent: Option<&T>,
+    cascade: &Cascade,
+) -> bool {
+    let parent = parent.cloned().unwrap_or_default();
+    let mut new = parent.clone();
+    for (name, value) in declarations(node, cascade, T::PROPERTIES) {
+        let value = value.trim();
+        // every property here is inherited, so `unset` is the same as `inherit`
+        if value.eq_ignore_ascii_case("inherit") || value.eq_ignore_ascii_case("unset") {
+            new.copy_property(&name, &parent);
+        } else if value.eq_ignore_ascii_case("initial") {
+            new.copy_property(&name, &T::default());
+        } else {
+            new.apply(&name, value, &parent);
+        }
+    }
+
+    if *state != new {
+        *state = new;
+        true
+    } else {
         false
     }
 }
 
+fn parse_color(value: &str) -> Option<CssColor> {
+    let mut value = ParserInput::new(value);
+    let mut parser = Parser::new(&mut value);
+    CssColor::parse(&mut parser).ok()
+}
+
+/// What is painted behind the content and the border of an element.
 #[derive(Clone, PartialEq, Debug)]
-pub(crate) struct ForgroundColor(pub CssColor);
-impl ParentDepState for ForgroundColor {
-    type Ctx = ();
-    type DepState = Self;
-    const NODE_MASK: NodeMask = NodeMask::new_with_attrs(AttributeMask::Static(&["color"]));
-
-    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, _: &Self::Ctx) -> bool {
-        let new = if let Some(parent) = parent {
-            parent.0.clone()
-        } else if let Some(color_attr) = node.attributes().next() {
-            if let Some(as_text) = color_attr.value.as_text() {
-                let mut value = ParserInput::new(as_text);
-                let mut parser = Parser::new(&mut value);
-                if let Ok(new_color) = CssColor::parse(&mut parser) {
-                    new_color
-                } else {
-                    return false;
+pub(crate) struct Background {
+    pub color: CssColor,
+    /// The image layers painted above the color, from the top one to the bottom one.
+    pub images: Vec<Image>,
+    pub clip: BackgroundClip,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background {
+            color: CssColor::RGBA(RGBA::new(255, 255, 255, 0)),
+            images: Vec::new(),
+            clip: BackgroundClip::default(),
+        }
+    }
+}
+
+impl NodeDepState<()> for Background {
+    type Ctx = Cascade;
+
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(BACKGROUND_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), cascade: &Self::Ctx) -> bool {
+        let mut new = Background::default();
+        for (name, value) in declarations(&node, cascade, BACKGROUND_PROPERTIES) {
+            match name.as_str() {
+                "background-color" => {
+                    if let Some(color) = parse_color(&value) {
+                        new.color = color;
+                    }
                 }
-            } else {
-                return false;
+                "background-clip" => {
+                    if let Some(clip) = BackgroundClip::parse(&value) {
+                        new.clip = clip;
+                    }
+                }
+                "background-image" | "background" => {
+                    let mut value = ParserInput::new(&value);
+                    let mut parser = Parser::new(&mut value);
+                    match Property::parse(
+                        name.as_str().into(),
+                        &mut parser,
+                        &ParserOptions::default(),
+                    ) {
+                        Ok(Property::BackgroundImage(images)) => {
+                            new.images = images.into_iter().collect();
+                        }
+                        // the shorthand resets every longhand, and only the last layer has a color
+                        Ok(Property::Background(layers)) => {
+                            new.images = layers.iter().map(|l| l.image.clone()).collect();
+                            if let Some(last) = layers.last() {
+                                new.color = last.color.clone();
+                                new.clip = BackgroundClip::from(&last.clip);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
             }
-        } else {
-            return false;
-        };
+        }
 
-        if self.0 != new {
-            *self = Self(new);
+        if self != &mut new {
+            *self = new;
             true
         } else {
             false
@@ -94,89 +276,230 @@ impl ParentDepState for ForgroundColor {
     }
 }
 
+// the inline style can set any of the properties
+const BACKGROUND_PROPERTIES: &[&str] = &sorted_str_slice!([
+    "background",
+    "background-clip",
+    "background-color",
+    "background-image",
+    "style"
+]);
+
+/// The box the background is painted in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum BackgroundClip {
+    BorderBox,
+    PaddingBox,
+    ContentBox,
+}
+
+impl Default for BackgroundClip {
+    fn default() -> Self {
+        BackgroundClip::BorderBox
+    }
+}
+
+impl BackgroundClip {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "border-box" => Some(BackgroundClip::BorderBox),
+            "padding-box" => Some(BackgroundClip::PaddingBox),
+            "content-box" => Some(BackgroundClip::ContentBox),
+            _ => None,
+        }
+    }
+}
+
+impl From<&background::BackgroundClip> for BackgroundClip {
+    fn from(clip: &background::BackgroundClip) -> Self {
+        match clip {
+            background::BackgroundClip::PaddingBox => BackgroundClip::PaddingBox,
+            background::BackgroundClip::ContentBox => BackgroundClip::ContentBox,
+            // text clipping isn't supported
+            _ => BackgroundClip::BorderBox,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct ForgroundColor(pub CssColor);
+
+impl Default for ForgroundColor {
+    fn default() -> Self {
+        ForgroundColor(CssColor::RGBA(RGBA::new(0, 0, 0, 255)))
+    }
+}
+
+impl Inherited for ForgroundColor {
+    const PROPERTIES: &'static [&'static str] = COLOR_PROPERTIES;
+
+    fn apply(&mut self, _name: &str, value: &str, _parent: &Self) {
+        if let Some(color) = parse_color(value) {
+            self.0 = color;
+        }
+    }
+
+    fn copy_property(&mut self, _name: &str, from: &Self) {
+        *self = from.clone();
+    }
+}
+
+impl ParentDepState for ForgroundColor {
+    type Ctx = Cascade;
+    type DepState = Self;
+    const NODE_MASK: NodeMask = NodeMask::new_with_attrs(AttributeMask::Static(COLOR_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, cascade: &Self::Ctx) -> bool {
+        reduce_inherited(self, &node, parent, cascade)
+    }
+}
+
+const COLOR_PROPERTIES: &[&str] = &sorted_str_slice!(["color", "style"]);
+
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) struct Border {
     pub colors: BorderColor,
     pub width: BorderWidth,
+    pub styles: BorderStyle,
     pub radius: BorderRadius,
 }
 
-impl NodeDepState<()> for Border {
-    type Ctx = ();
-
-    const NODE_MASK: NodeMask =
-        NodeMask::new_with_attrs(AttributeMask::Static(&sorted_str_slice!([
-            "border-color",
-            "border-top-color",
-            "border-right-color",
-            "border-bottom-color",
-            "border-left-color",
-            "border-radius",
-            "border-top-left-radius",
-            "border-top-right-radius",
-            "border-bottom-right-radius",
-            "border-bottom-left-radius",
-            "border-width"
-            "border-top-width"
-            "border-right-width"
-            "border-bottom-width"
-            "border-left-width"
-        ])));
-
-    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), _: &Self::Ctx) -> bool {
+impl Border {
+    /// Collect the border properties that apply to a node.
+    pub(crate) fn from_node(node: &NodeView<'_>, cascade: &Cascade) -> Self {
         let mut new = Border::default();
-        for a in node.attributes() {
-            let mut value = ParserInput::new(a.value.as_text().unwrap());
+        for (name, value) in declarations(node, cascade, BORDER_PROPERTIES) {
+            let mut value = ParserInput::new(&value);
             let mut parser = Parser::new(&mut value);
-            match Property::parse(a.name.into(), &mut parser, &ParserOptions::default()).unwrap() {
-                Property::BorderColor(c) => {
+            match Property::parse(name.as_str().into(), &mut parser, &ParserOptions::default()) {
+                Ok(Property::BorderColor(c)) => {
                     new.colors = c;
                 }
-                Property::BorderTopColor(c) => {
+                Ok(Property::BorderTopColor(c)) => {
                     new.colors.top = c;
                 }
-                Property::BorderRightColor(c) => {
+                Ok(Property::BorderRightColor(c)) => {
                     new.colors.right = c;
                 }
-                Property::BorderBottomColor(c) => {
+                Ok(Property::BorderBottomColor(c)) => {
                     new.colors.bottom = c;
                 }
-                Property::BorderLeftColor(c) => {
+                Ok(Property::BorderLeftColor(c)) => {
                     new.colors.left = c;
                 }
-                Property::BorderRadius(r, _) => {
+                Ok(Property::BorderRadius(r, _)) => {
                     new.radius = r;
                 }
-                Property::BorderTopLeftRadius(r, _) => {
+                Ok(Property::BorderTopLeftRadius(r, _)) => {
                     new.radius.top_left = r;
                 }
-                Property::BorderTopRightRadius(r, _) => {
+                Ok(Property::BorderTopRightRadius(r, _)) => {
                     new.radius.top_right = r;
                 }
-                Property::BorderBottomRightRadius(r, _) => {
+                Ok(Property::BorderBottomRightRadius(r, _)) => {
                     new.radius.bottom_right = r;
                 }
-                Property::BorderBottomLeftRadius(r, _) => {
+                Ok(Property::BorderBottomLeftRadius(r, _)) => {
                     new.radius.bottom_left = r;
                 }
-                Property::BorderWidth(width) => {
+                Ok(Property::BorderWidth(width)) => {
                     new.width = width;
                 }
-                Property::BorderTopWidth(width) => {
+                Ok(Property::BorderTopWidth(width)) => {
                     new.width.top = width;
                 }
-                Property::BorderRightWidth(width) => {
+                Ok(Property::BorderRightWidth(width)) => {
                     new.width.right = width;
                 }
-                Property::BorderBottomWidth(width) => {
+                Ok(Property::BorderBottomWidth(width)) => {
                     new.width.bottom = width;
                 }
-                Property::BorderLeftWidth(width) => {
+                Ok(Property::BorderLeftWidth(width)) => {
                     new.width.left = width;
                 }
+                Ok(Property::BorderStyle(styles)) => {
+                    new.styles = styles;
+                }
+                Ok(Property::BorderTopStyle(style)) => {
+                    new.styles.top = style;
+                }
+                Ok(Property::BorderRightStyle(style)) => {
+                    new.styles.right = style;
+                }
+                Ok(Property::BorderBottomStyle(style)) => {
+                    new.styles.bottom = style;
+                }
+                Ok(Property::BorderLeftStyle(style)) => {
+                    new.styles.left = style;
+                }
+                Ok(Property::Border(border)) => {
+                    new.width = BorderWidth {
+                        top: border.width.clone(),
+                        right: border.width.clone(),
+                        bottom: border.width.clone(),
+                        left: border.width,
+                    };
+                    new.styles = BorderStyle {
+                        top: border.style,
+                        right: border.style,
+                        bottom: border.style,
+                        left: border.style,
+                    };
+                    new.colors = BorderColor {
+                        top: border.color.clone(),
+                        right: border.color.clone(),
+                        bottom: border.color.clone(),
+                        left: border.color,
+                    };
+                }
+                Ok(Property::BorderTop(border)) => {
+                    new.width.top = border.width;
+                    new.styles.top = border.style;
+                    new.colors.top = border.color;
+                }
+                Ok(Property::BorderRight(border)) => {
+                    new.width.right = border.width;
+                    new.styles.right = border.style;
+                    new.colors.right = border.color;
+                }
+                Ok(Property::BorderBottom(border)) => {
+                    new.width.bottom = border.width;
+                    new.styles.bottom = border.style;
+                    new.colors.bottom = border.color;
+                }
+                Ok(Property::BorderLeft(border)) => {
+                    new.width.left = border.width;
+                    new.styles.left = border.style;
+                    new.colors.left = border.color;
+                }
                 _ => {}
             }
         }
+        new
+    }
+
+    /// The space each side of the border takes up.
+    pub(crate) fn widths(&self, rect: &Size<f32>, viewport_size: &Size<u32>) -> Insets {
+        let width = |width: &BorderSideWidth, style: &LineStyle| match style {
+            LineStyle::None | LineStyle::Hidden => 0.0,
+            _ => width.resolve(Axis::Min, rect, viewport_size),
+        };
+        Insets::new(
+            width(&self.width.left, &self.styles.left),
+            width(&self.width.top, &self.styles.top),
+            width(&self.width.right, &self.styles.right),
+            width(&self.width.bottom, &self.styles.bottom),
+        )
+    }
+}
+
+impl NodeDepState<()> for Border {
+    type Ctx = Cascade;
+
+    const NODE_MASK: NodeMask = NodeMask::new_with_attrs(AttributeMask::Static(BORDER_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), cascade: &Self::Ctx) -> bool {
+        let mut new = Border::from_node(&node, cascade);
 
         if self != &mut new {
             *self = new;
@@ -187,6 +510,35 @@ impl NodeDepState<()> for Border {
     }
 }
 
+pub(crate) const BORDER_PROPERTIES: &[&str] = &sorted_str_slice!([
+    "border",
+    "border-top",
+    "border-right",
+    "border-bottom",
+    "border-left",
+    "border-style",
+    "border-top-style",
+    "border-right-style",
+    "border-bottom-style",
+    "border-left-style",
+    "border-color",
+    "border-top-color",
+    "border-right-color",
+    "border-bottom-color",
+    "border-left-color",
+    "border-radius",
+    "border-top-left-radius",
+    "border-top-right-radius",
+    "border-bottom-right-radius",
+    "border-bottom-left-radius",
+    "border-width",
+    "border-top-width",
+    "border-right-width",
+    "border-bottom-width",
+    "border-left-width",
+    "style"
+]);
+
 impl Default for Border {
     fn default() -> Self {
         Border {
@@ -198,11 +550,711 @@ impl Default for Border {
             },
             radius: BorderRadius::default(),
             width: BorderWidth {
-                top: BorderSideWidth::default(),
-                right: BorderSideWidth::default(),
-                bottom: BorderSideWidth::default(),
-                left: BorderSideWidth::default(),
+                top: BorderSideWidth::Medium,
+                right: BorderSideWidth::Medium,
+                bottom: BorderSideWidth::Medium,
+                left: BorderSideWidth::Medium,
+            },
+            // there is no border until a style is set
+            styles: BorderStyle {
+                top: LineStyle::None,
+                right: LineStyle::None,
+                bottom: LineStyle::None,
+                left: LineStyle::None,
+            },
+        }
+    }
+}
+
+/// The shadows cast by the border box of an element, from the top one to the bottom one.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct BoxShadows(pub Vec<BoxShadow>);
+
+impl NodeDepState<()> for BoxShadows {
+    type Ctx = Cascade;
+
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(BOX_SHADOW_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), cascade: &Self::Ctx) -> bool {
+        let mut new = BoxShadows::default();
+        for (name, value) in declarations(&node, cascade, BOX_SHADOW_PROPERTIES) {
+            if value.trim().eq_ignore_ascii_case("none") {
+                new = BoxShadows::default();
+                continue;
+            }
+            let mut value = ParserInput::new(&value);
+            let mut parser = Parser::new(&mut value);
+            if let Ok(Property::BoxShadow(shadows, _)) =
+                Property::parse(name.as_str().into(), &mut parser, &ParserOptions::default())
+            {
+                new = BoxShadows(shadows.into_iter().collect());
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+const BOX_SHADOW_PROPERTIES: &[&str] = &sorted_str_slice!(["box-shadow", "style"]);
+
+/// What happens to the content of an element that doesn't fit in its padding box.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Overflow {
+    pub x: OverflowKeyword,
+    pub y: OverflowKeyword,
+}
+
+impl Default for Overflow {
+    fn default() -> Self {
+        Overflow {
+            x: OverflowKeyword::Visible,
+            y: OverflowKeyword::Visible,
+        }
+    }
+}
+
+impl Overflow {
+    /// Whether the children are clipped to the padding box.
+    pub(crate) fn clips(&self) -> bool {
+        !matches!(self.x, OverflowKeyword::Visible) || !matches!(self.y, OverflowKeyword::Visible)
+    }
+
+    /// Whether the user can scroll the content horizontally.
+    pub(crate) fn scrolls_x(&self) -> bool {
+        matches!(self.x, OverflowKeyword::Scroll | OverflowKeyword::Auto)
+    }
+
+    /// Whether the user can scroll the content vertically.
+    pub(crate) fn scrolls_y(&self) -> bool {
+        matches!(self.y, OverflowKeyword::Scroll | OverflowKeyword::Auto)
+    }
+}
+
+impl NodeDepState<()> for Overflow {
+    type Ctx = Cascade;
+
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(OVERFLOW_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), cascade: &Self::Ctx) -> bool {
+        let mut new = Overflow::default();
+        for (name, value) in declarations(&node, cascade, OVERFLOW_PROPERTIES) {
+            let mut value = ParserInput::new(&value);
+            let mut parser = Parser::new(&mut value);
+            match Property::parse(name.into(), &mut parser, &ParserOptions::default()) {
+                Ok(Property::Overflow(overflow)) => {
+                    new.x = overflow.x;
+                    new.y = overflow.y;
+                }
+                Ok(Property::OverflowX(x)) => new.x = x,
+                Ok(Property::OverflowY(y)) => new.y = y,
+                _ => {}
+            }
+        }
+        // an axis can't be visible if the other one clips
+        let clips = |k: &OverflowKeyword| {
+            matches!(
+                k,
+                OverflowKeyword::Hidden | OverflowKeyword::Scroll | OverflowKeyword::Auto
+            )
+        };
+        if matches!(new.x, OverflowKeyword::Visible) && clips(&new.y) {
+            new.x = OverflowKeyword::Auto;
+        }
+        if matches!(new.y, OverflowKeyword::Visible) && clips(&new.x) {
+            new.y = OverflowKeyword::Auto;
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+const OVERFLOW_PROPERTIES: &[&str] =
+    &sorted_str_slice!(["overflow", "overflow-x", "overflow-y", "style"]);
+
+/// How the content of a replaced element like an `img` is sized and placed in its content box.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct ObjectFit {
+    pub fit: FitKeyword,
+    pub position: Position,
+}
+
+impl Default for ObjectFit {
+    fn default() -> Self {
+        ObjectFit {
+            fit: FitKeyword::Fill,
+            position: Position {
+                x: PositionComponent::Center,
+                y: PositionComponent::Center,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum FitKeyword {
+    /// Stretched to fill the box.
+    Fill,
+    /// Scaled to fit inside the box, keeping the aspect ratio.
+    Contain,
+    /// Scaled to cover the whole box, keeping the aspect ratio.
+    Cover,
+    /// Kept at its natural size.
+    None,
+    /// Like `None` or `Contain`, whichever makes it smaller.
+    ScaleDown,
+}
+
+impl NodeDepState<()> for ObjectFit {
+    type Ctx = Cascade;
+
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(OBJECT_FIT_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, _sibling: (), cascade: &Self::Ctx) -> bool {
+        let mut new = ObjectFit::default();
+        for (name, value) in declarations(&node, cascade, OBJECT_FIT_PROPERTIES) {
+            match name.as_str() {
+                "object-fit" => {
+                    new.fit = match value.trim().to_ascii_lowercase().as_str() {
+                        "fill" => FitKeyword::Fill,
+                        "contain" => FitKeyword::Contain,
+                        "cover" => FitKeyword::Cover,
+                        "none" => FitKeyword::None,
+                        "scale-down" => FitKeyword::ScaleDown,
+                        _ => continue,
+                    }
+                }
+                "object-position" => {
+                    let mut value = ParserInput::new(&value);
+                    let mut parser = Parser::new(&mut value);
+                    if let Ok(position) = Position::parse(&mut parser) {
+                        new.position = position;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+const OBJECT_FIT_PROPERTIES: &[&str] =
+    &sorted_str_slice!(["object-fit", "object-position", "style"]);
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum FontFamilyName {
+    Generic(GenericFontFamily),
+    Named(String),
+}
+
+/// The font used to draw text, resolved against the parent's font.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Font {
+    /// The families to try in order of preference.
+    pub family: Vec<FontFamilyName>,
+    /// The size of the font in pixels.
+    pub size: f64,
+    /// The weight of the font from 1 to 1000.
+    pub weight: u16,
+    pub italic: bool,
+    pub line_height: LineHeight,
+}
+
+/// The height of each line of text.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum LineHeight {
+    /// The height of the font.
+    Normal,
+    /// A multiple of the font size. This is inherited as a factor, so it scales with the
+    /// children's font size.
+    Factor(f64),
+    /// A height in pixels.
+    Px(f64),
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Font {
+            family: vec![FontFamilyName::Generic(GenericFontFamily::SystemUI)],
+            size: DEFAULT_FONT_SIZE,
+            weight: 400,
+            italic: false,
+            line_height: LineHeight::Normal,
+        }
+    }
+}
+
+impl Font {
+    fn apply_family(&mut self, family: &[FontFamily]) {
+        self.family = family
+            .iter()
+            .map(|f| match f {
+                FontFamily::Generic(g) => FontFamilyName::Generic(*g),
+                FontFamily::FamilyName(name) => FontFamilyName::Named(name.to_string()),
+            })
+            .collect();
+    }
+
+    /// Resolve a font size relative to the parent's font size.
+    fn apply_size(&mut self, size: &FontSize, parent_size: f64) {
+        self.size = match size {
+            FontSize::Length(DimensionPercentage::Dimension(length)) => match length {
+                LengthValue::Em(em) => *em as f64 * parent_size,
+                LengthValue::Rem(rem) => *rem as f64 * DEFAULT_FONT_SIZE,
+                LengthValue::Ex(ex) => *ex as f64 * parent_size / 2.0,
+                LengthValue::Ch(ch) => *ch as f64 * parent_size / 2.0,
+                _ => length.to_px().map(|px| px as f64).unwrap_or(parent_size),
+            },
+            FontSize::Length(DimensionPercentage::Percentage(p)) => p.0 as f64 * parent_size,
+            FontSize::Length(DimensionPercentage::Calc(_)) => parent_size,
+            FontSize::Absolute(size) => match size {
+                AbsoluteFontSize::XXSmall => DEFAULT_FONT_SIZE * 3.0 / 5.0,
+                AbsoluteFontSize::XSmall => DEFAULT_FONT_SIZE * 3.0 / 4.0,
+                AbsoluteFontSize::Small => DEFAULT_FONT_SIZE * 8.0 / 9.0,
+                AbsoluteFontSize::Medium => DEFAULT_FONT_SIZE,
+                AbsoluteFontSize::Large => DEFAULT_FONT_SIZE * 6.0 / 5.0,
+                AbsoluteFontSize::XLarge => DEFAULT_FONT_SIZE * 3.0 / 2.0,
+                AbsoluteFontSize::XXLarge => DEFAULT_FONT_SIZE * 2.0,
+            },
+            FontSize::Relative(RelativeFontSize::Smaller) => parent_size / 1.2,
+            FontSize::Relative(RelativeFontSize::Larger) => parent_size * 1.2,
+        };
+    }
+
+    /// Resolve a font weight relative to the parent's font weight.
+    fn apply_weight(&mut self, weight: &FontWeight, parent_weight: u16) {
+        self.weight = match weight {
+            FontWeight::Absolute(AbsoluteFontWeight::Weight(w)) => w.clamp(1.0, 1000.0) as u16,
+            FontWeight::Absolute(AbsoluteFontWeight::Normal) => 400,
+            FontWeight::Absolute(AbsoluteFontWeight::Bold) => 700,
+            // https://www.w3.org/TR/css-fonts-4/#relative-weights
+            FontWeight::Bolder => match parent_weight {
+                0..=349 => 400,
+                350..=549 => 700,
+                _ => parent_weight.max(900),
+            },
+            FontWeight::Lighter => match parent_weight {
+                0..=549 => parent_weight.min(100),
+                550..=749 => 400,
+                _ => 700,
+            },
+        };
+    }
+
+    fn apply_style(&mut self, style: &FontStyle) {
+        self.italic = !matches!(style, FontStyle::Normal);
+    }
+
+    /// Resolve a line height relative to this font's size.
+    fn apply_line_height(&mut self, line_height: &CssLineHeight) {
+        self.line_height = match line_height {
+            CssLineHeight::Normal => LineHeight::Normal,
+            CssLineHeight::Number(factor) => LineHeight::Factor(*factor as f64),
+            CssLineHeight::Length(DimensionPercentage::Dimension(length)) => match length {
+                LengthValue::Em(em) => LineHeight::Px(*em as f64 * self.size),
+                LengthValue::Rem(rem) => LineHeight::Px(*rem as f64 * DEFAULT_FONT_SIZE),
+                _ => length
+                    .to_px()
+                    .map_or(LineHeight::Normal, |px| LineHeight::Px(px as f64)),
             },
+            CssLineHeight::Length(DimensionPercentage::Percentage(p)) => {
+                LineHeight::Px(p.0 as f64 * self.size)
+            }
+            CssLineHeight::Length(DimensionPercentage::Calc(_)) => LineHeight::Normal,
+        };
+    }
+
+    /// The height of a line of text given the height the font itself needs.
+    pub(crate) fn line_box_height(&self, natural_height: f64) -> f64 {
+        match self.line_height {
+            LineHeight::Normal => natural_height,
+            LineHeight::Factor(factor) => factor * self.size,
+            LineHeight::Px(px) => px,
+        }
+    }
+}
+
+impl Inherited for Font {
+    const PROPERTIES: &'static [&'static str] = FONT_PROPERTIES;
+
+    fn apply(&mut self, name: &str, value: &str, parent: &Self) {
+        let mut value = ParserInput::new(value);
+        let mut parser = Parser::new(&mut value);
+        match Property::parse(name.into(), &mut parser, &ParserOptions::default()) {
+            Ok(Property::Font(font)) => {
+                self.apply_family(&font.family);
+                self.apply_size(&font.size, parent.size);
+                self.apply_weight(&font.weight, parent.weight);
+                self.apply_style(&font.style);
+                self.apply_line_height(&font.line_height);
+            }
+            Ok(Property::FontFamily(family)) => self.apply_family(&family),
+            Ok(Property::FontSize(size)) => self.apply_size(&size, parent.size),
+            Ok(Property::FontWeight(weight)) => self.apply_weight(&weight, parent.weight),
+            Ok(Property::FontStyle(style)) => self.apply_style(&style),
+            Ok(Property::LineHeight(line_height)) => self.apply_line_height(&line_height),
+            _ => {}
+        }
+    }
+
+    fn copy_property(&mut self, name: &str, from: &Self) {
+        match name {
+            "font-family" => self.family = from.family.clone(),
+            "font-size" => self.size = from.size,
+            "font-weight" => self.weight = from.weight,
+            "font-style" => self.italic = from.italic,
+            "line-height" => self.line_height = from.line_height,
+            _ => *self = from.clone(),
+        }
+    }
+}
+
+impl ParentDepState for Font {
+    type Ctx = Cascade;
+    type DepState = Self;
+
+    const NODE_MASK: NodeMask = NodeMask::new_with_attrs(AttributeMask::Static(FONT_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, cascade: &Self::Ctx) -> bool {
+        reduce_inherited(self, &node, parent, cascade)
+    }
+}
+
+const FONT_PROPERTIES: &[&str] = &sorted_str_slice!([
+    "font",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "line-height",
+    "style"
+]);
+
+/// The horizontal alignment of lines of text within their box.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum TextAlign {
+    Left,
+    Right,
+    Center,
+}
+
+impl Default for TextAlign {
+    fn default() -> Self {
+        TextAlign::Left
+    }
+}
+
+impl TextAlign {
+    /// The offset of a line from the left edge of a box of the given width.
+    pub(crate) fn offset(&self, line_width: f64, box_width: f64) -> f64 {
+        match self {
+            TextAlign::Left => 0.0,
+            TextAlign::Right => box_width - line_width,
+            TextAlign::Center => (box_width - line_width) / 2.0,
+        }
+    }
+}
+
+impl Inherited for TextAlign {
+    const PROPERTIES: &'static [&'static str] = TEXT_ALIGN_PROPERTIES;
+
+    fn apply(&mut self, _name: &str, value: &str, _parent: &Self) {
+        *self = match value.to_ascii_lowercase().as_str() {
+            // text is always laid out left to right
+            "left" | "start" | "justify" => TextAlign::Left,
+            "right" | "end" => TextAlign::Right,
+            "center" => TextAlign::Center,
+            _ => return,
+        };
+    }
+
+    fn copy_property(&mut self, _name: &str, from: &Self) {
+        *self = *from;
+    }
+}
+
+impl ParentDepState for TextAlign {
+    type Ctx = Cascade;
+    type DepState = Self;
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(TEXT_ALIGN_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, cascade: &Self::Ctx) -> bool {
+        reduce_inherited(self, &node, parent, cascade)
+    }
+}
+
+const TEXT_ALIGN_PROPERTIES: &[&str] = &sorted_str_slice!(["style", "text-align"]);
+
+/// Whether a node is painted. Hidden nodes still take up space in the layout.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Visibility {
+    Visible,
+    Hidden,
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility::Visible
+    }
+}
+
+impl Inherited for Visibility {
+    const PROPERTIES: &'static [&'static str] = VISIBILITY_PROPERTIES;
+
+    fn apply(&mut self, _name: &str, value: &str, _parent: &Self) {
+        *self = match value.to_ascii_lowercase().as_str() {
+            "visible" => Visibility::Visible,
+            "hidden" | "collapse" => Visibility::Hidden,
+            _ => return,
+        };
+    }
+
+    fn copy_property(&mut self, _name: &str, from: &Self) {
+        *self = *from;
+    }
+}
+
+impl ParentDepState for Visibility {
+    type Ctx = Cascade;
+    type DepState = Self;
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(VISIBILITY_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, cascade: &Self::Ctx) -> bool {
+        reduce_inherited(self, &node, parent, cascade)
+    }
+}
+
+const VISIBILITY_PROPERTIES: &[&str] = &sorted_str_slice!(["style", "visibility"]);
+
+/// The mouse cursor shown over a node.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub(crate) struct Cursor(pub CursorIcon);
+
+impl Inherited for Cursor {
+    const PROPERTIES: &'static [&'static str] = CURSOR_PROPERTIES;
+
+    fn apply(&mut self, _name: &str, value: &str, _parent: &Self) {
+        // the cursor may list image fallbacks before the keyword, only the keyword is supported
+        let keyword = value.rsplit(',').next().unwrap_or_default().trim();
+        self.0 = match keyword.to_ascii_lowercase().as_str() {
+            "auto" | "default" => CursorIcon::Default,
+            "pointer" => CursorIcon::Hand,
+            "text" => CursorIcon::Text,
+            "vertical-text" => CursorIcon::VerticalText,
+            "crosshair" => CursorIcon::Crosshair,
+            "move" => CursorIcon::Move,
+            "grab" => CursorIcon::Grab,
+            "grabbing" => CursorIcon::Grabbing,
+            "wait" => CursorIcon::Wait,
+            "progress" => CursorIcon::Progress,
+            "help" => CursorIcon::Help,
+            "not-allowed" => CursorIcon::NotAllowed,
+            "no-drop" => CursorIcon::NoDrop,
+            "copy" => CursorIcon::Copy,
+            "alias" => CursorIcon::Alias,
+            "context-menu" => CursorIcon::ContextMenu,
+            "cell" => CursorIcon::Cell,
+            "all-scroll" => CursorIcon::AllScroll,
+            "zoom-in" => CursorIcon::ZoomIn,
+            "zoom-out" => CursorIcon::ZoomOut,
+            "col-resize" => CursorIcon::ColResize,
+            "row-resize" => CursorIcon::RowResize,
+            "n-resize" => CursorIcon::NResize,
+            "e-resize" => CursorIcon::EResize,
+            "s-resize" => CursorIcon::SResize,
+            "w-resize" => CursorIcon::WResize,
+            "ne-resize" => CursorIcon::NeResize,
+            "nw-resize" => CursorIcon::NwResize,
+            "se-resize" => CursorIcon::SeResize,
+            "sw-resize" => CursorIcon::SwResize,
+            "ew-resize" => CursorIcon::EwResize,
+            "ns-resize" => CursorIcon::NsResize,
+            "nesw-resize" => CursorIcon::NeswResize,
+            "nwse-resize" => CursorIcon::NwseResize,
+            _ => return,
+        };
+    }
+
+    fn copy_property(&mut self, _name: &str, from: &Self) {
+        *self = *from;
+    }
+}
+
+impl ParentDepState for Cursor {
+    type Ctx = Cascade;
+    type DepState = Self;
+    const NODE_MASK: NodeMask = NodeMask::new_with_attrs(AttributeMask::Static(CURSOR_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, cascade: &Self::Ctx) -> bool {
+        reduce_inherited(self, &node, parent, cascade)
+    }
+}
+
+const CURSOR_PROPERTIES: &[&str] = &sorted_str_slice!(["cursor", "style"]);
+
+/// The marker drawn before each `li` element. List items have no marker unless a stylesheet
+/// gives them one, which the default stylesheet does through their list.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum ListStyleType {
+    None,
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+}
+
+impl Default for ListStyleType {
+    fn default() -> Self {
+        ListStyleType::None
+    }
+}
+
+impl ListStyleType {
+    /// The text of the marker for the list item at the given index.
+    pub(crate) fn marker(&self, index: usize) -> Option<String> {
+        match self {
+            ListStyleType::None => None,
+            ListStyleType::Disc => Some("•".to_string()),
+            ListStyleType::Circle => Some("◦".to_string()),
+            ListStyleType::Square => Some("▪".to_string()),
+            ListStyleType::Decimal => Some(format!("{}.", index + 1)),
+        }
+    }
+}
+
+impl Inherited for ListStyleType {
+    const PROPERTIES: &'static [&'static str] = LIST_STYLE_PROPERTIES;
+
+    fn apply(&mut self, _name: &str, value: &str, _parent: &Self) {
+        // the list-style shorthand may also contain a position or an image
+        for keyword in value.split_whitespace() {
+            *self = match keyword.to_ascii_lowercase().as_str() {
+                "none" => ListStyleType::None,
+                "disc" => ListStyleType::Disc,
+                "circle" => ListStyleType::Circle,
+                "square" => ListStyleType::Square,
+                "decimal" => ListStyleType::Decimal,
+                _ => continue,
+            };
+        }
+    }
+
+    fn copy_property(&mut self, _name: &str, from: &Self) {
+        *self = *from;
+    }
+}
+
+impl ParentDepState for ListStyleType {
+    type Ctx = Cascade;
+    type DepState = Self;
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(LIST_STYLE_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, cascade: &Self::Ctx) -> bool {
+        reduce_inherited(self, &node, parent, cascade)
+    }
+}
+
+const LIST_STYLE_PROPERTIES: &[&str] =
+    &sorted_str_slice!(["list-style", "list-style-type", "style"]);
+
+/// Whether the mouse can hit a node or goes through to whatever is below it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum PointerEvents {
+    Auto,
+    None,
+}
+
+impl Default for PointerEvents {
+    fn default() -> Self {
+        PointerEvents::Auto
+    }
+}
+
+impl Inherited for PointerEvents {
+    const PROPERTIES: &'static [&'static str] = POINTER_EVENTS_PROPERTIES;
+
+    fn apply(&mut self, _name: &str, value: &str, _parent: &Self) {
+        *self = match value.to_ascii_lowercase().as_str() {
+            "auto" => PointerEvents::Auto,
+            "none" => PointerEvents::None,
+            _ => return,
+        };
+    }
+
+    fn copy_property(&mut self, _name: &str, from: &Self) {
+        *self = *from;
+    }
+}
+
+impl ParentDepState for PointerEvents {
+    type Ctx = Cascade;
+    type DepState = Self;
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(POINTER_EVENTS_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, cascade: &Self::Ctx) -> bool {
+        reduce_inherited(self, &node, parent, cascade)
+    }
+}
+
+const POINTER_EVENTS_PROPERTIES: &[&str] = &sorted_str_slice!(["pointer-events", "style"]);
+
+/// The shadows cast by text, from the top one to the bottom one.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct TextShadows(pub Vec<TextShadow>);
+
+impl Inherited for TextShadows {
+    const PROPERTIES: &'static [&'static str] = TEXT_SHADOW_PROPERTIES;
+
+    fn apply(&mut self, name: &str, value: &str, _parent: &Self) {
+        if value.trim().eq_ignore_ascii_case("none") {
+            *self = TextShadows::default();
+            return;
+        }
+        let mut value = ParserInput::new(value);
+        let mut parser = Parser::new(&mut value);
+        if let Ok(Property::TextShadow(shadows)) =
+            Property::parse(name.into(), &mut parser, &ParserOptions::default())
+        {
+            *self = TextShadows(shadows.into_iter().collect());
         }
     }
+
+    fn copy_property(&mut self, _name: &str, from: &Self) {
+        *self = from.clone();
+    }
 }
+
+impl ParentDepState for TextShadows {
+    type Ctx = Cascade;
+    type DepState = Self;
+    const NODE_MASK: NodeMask =
+        NodeMask::new_with_attrs(AttributeMask::Static(TEXT_SHADOW_PROPERTIES));
+
+    fn reduce(&mut self, node: NodeView<'_>, parent: Option<&Self>, cascade: &Self::Ctx) -> bool {
+        reduce_inherited(self, &node, parent, cascade)
+    }
+}
+
+const TEXT_SHADOW_PROPERTIES: &[&str] = &sorted_str_slice!(["style", "text-shadow"]);
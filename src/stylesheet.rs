@@ -0,0 +1,1101 @@
+use std::{cell::RefCell, rc::Rc};
+
+use cssparser::{
+    AtRuleParser, CowRcStr, DeclarationListParser, DeclarationParser, ParseError, Parser,
+    ParserInput, ParserState, QualifiedRuleParser, RuleListParser, Token,
+};
+use dioxus::core::{ElementId, VNode};
+use dioxus::prelude::VirtualDom;
+use dioxus_native_core::node_ref::{AttributeMask, NodeMask};
+use dioxus_native_core::real_dom::NodeType;
+use fxhash::{FxHashMap, FxHashSet};
+use parcel_css::{properties::Property, stylesheet::ParserOptions};
+
+use crate::{is_document_node, Dom};
+
+/// The default styles for html elements that every app starts with.
+pub(crate) const DEFAULT_STYLESHEET: &str = include_str!("default.css");
+
+/// A single `name: value` pair from a rule.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Declaration {
+    pub name: String,
+    pub value: String,
+    pub important: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum AttributeOperator {
+    Exists,
+    /// `[attr=value]`
+    Equals(String),
+    /// `[attr~=value]`
+    Includes(String),
+    /// `[attr|=value]`
+    DashMatch(String),
+    /// `[attr^=value]`
+    Prefix(String),
+    /// `[attr$=value]`
+    Suffix(String),
+    /// `[attr*=value]`
+    Substring(String),
+}
+
+impl AttributeOperator {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            AttributeOperator::Exists => true,
+            AttributeOperator::Equals(v) => value == v,
+            AttributeOperator::Includes(v) => value.split_whitespace().any(|w| w == v),
+            AttributeOperator::DashMatch(v) => {
+                value == v
+                    || value
+                        .strip_prefix(v.as_str())
+                        .map_or(false, |r| r.starts_with('-'))
+            }
+            AttributeOperator::Prefix(v) => !v.is_empty() && value.starts_with(v.as_str()),
+            AttributeOperator::Suffix(v) => !v.is_empty() && value.ends_with(v.as_str()),
+            AttributeOperator::Substring(v) => !v.is_empty() && value.contains(v.as_str()),
+        }
+    }
+}
+
+/// A pseudo-class that matches elements in some interactive state.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PseudoClass {
+    /// `:hover`
+    Hover,
+    /// `:active`
+    Active,
+    /// `:focus`
+    Focus,
+    /// `:focus-visible`
+    FocusVisible,
+}
+
+impl PseudoClass {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "hover" => Some(PseudoClass::Hover),
+            "active" => Some(PseudoClass::Active),
+            "focus" => Some(PseudoClass::Focus),
+            "focus-visible" => Some(PseudoClass::FocusVisible),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, id: ElementId, states: &ElementStates) -> bool {
+        match self {
+            PseudoClass::Hover => states.hovered.contains(&id),
+            PseudoClass::Active => states.active.contains(&id),
+            PseudoClass::Focus => states.focused == Some(id),
+            PseudoClass::FocusVisible => states.focused == Some(id) && states.focus_visible,
+        }
+    }
+}
+
+/// The interactive state of the elements that pseudo-classes match against.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct ElementStates {
+    /// The element under the mouse and all of its ancestors.
+    pub hovered: Vec<ElementId>,
+    /// The element the mouse is pressed on and all of its ancestors.
+    pub active: Vec<ElementId>,
+    pub focused: Option<ElementId>,
+    /// If the focus was last moved with the keyboard.
+    pub focus_visible: bool,
+}
+
+/// A selector without combinators, e.g. `div.card#main[open]:hover`.
+#[derive(Clone, PartialEq, Debug, Default)]
+struct CompoundSelector {
+    /// If this is `:root`, which only matches the node that holds the document.
+    root: bool,
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, AttributeOperator)>,
+    pseudo_classes: Vec<PseudoClass>,
+}
+
+impl CompoundSelector {
+    fn specificity(&self) -> u32 {
+        let ids = self.id.is_some() as u32;
+        let classes = (self.root as usize
+            + self.classes.len()
+            + self.attributes.len()
+            + self.pseudo_classes.len()) as u32;
+        let types = self.tag.is_some() as u32;
+        (ids << 20) | (classes << 10) | types
+    }
+
+    fn matches(&self, element: &ElementInfo) -> bool {
+        if self.root == is_document_node(element.id) {
+            return false;
+        }
+        if let Some(tag) = &self.tag {
+            if tag != element.tag {
+                return false;
+            }
+        }
+        if let Some(id) = &self.id {
+            if element.attribute("id") != Some(id) {
+                return false;
+            }
+        }
+        if !self.classes.is_empty() {
+            let classes = element.attribute("class").unwrap_or_default();
+            if !self
+                .classes
+                .iter()
+                .all(|c| classes.split_whitespace().any(|class| class == c))
+            {
+                return false;
+            }
+        }
+        if !self
+            .pseudo_classes
+            .iter()
+            .all(|p| p.matches(element.id, element.states))
+        {
+            return false;
+        }
+        self.attributes.iter().all(|(name, operator)| {
+            element
+                .attribute(name)
+                .map_or(false, |value| operator.matches(value))
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Combinator {
+    /// `a b`
+    Descendant,
+    /// `a > b`
+    Child,
+}
+
+/// A complex selector like `ul.menu > li a`.
+#[derive(Clone, PartialEq, Debug)]
+struct Selector {
+    /// The rightmost compound selector that the element itself must match.
+    subject: CompoundSelector,
+    /// The compound selectors to the left of the subject, from right to left.
+    ancestors: Vec<(Combinator, CompoundSelector)>,
+}
+
+impl Selector {
+    fn specificity(&self) -> u32 {
+        self.ancestors
+            .iter()
+            .fold(self.subject.specificity(), |s, (_, c)| s + c.specificity())
+    }
+
+    fn matches(&self, element: &ElementInfo, rdom: &Dom, vdom: &VirtualDom) -> bool {
+        self.subject.matches(element)
+            && match_ancestors(
+                &self.ancestors,
+                rdom,
+                vdom,
+                element.states,
+                rdom[element.id].parent,
+            )
+    }
+}
+
+fn match_ancestors(
+    ancestors: &[(Combinator, CompoundSelector)],
+    rdom: &Dom,
+    vdom: &VirtualDom,
+    states: &ElementStates,
+    parent: Option<ElementId>,
+) -> bool {
+    let ((combinator, compound), rest) = match ancestors.split_first() {
+        Some(first) => first,
+        None => return true,
+    };
+    let mut current = parent;
+    while let Some(id) = current {
+        let element = match ElementInfo::new(rdom, vdom, states, id) {
+            Some(element) => element,
+            None => return false,
+        };
+        if compound.matches(&element) && match_ancestors(rest, rdom, vdom, states, rdom[id].parent)
+        {
+            return true;
+        }
+        if *combinator == Combinator::Child {
+            return false;
+        }
+        current = rdom[id].parent;
+    }
+    false
+}
+
+/// The parts of an element that selectors can match against.
+struct ElementInfo<'a> {
+    id: ElementId,
+    tag: &'a str,
+    /// The node in the vdom, which the root doesn't have.
+    vnode: Option<&'a VNode<'a>>,
+    states: &'a ElementStates,
+}
+
+impl<'a> ElementInfo<'a> {
+    fn new(
+        rdom: &'a Dom,
+        vdom: &'a VirtualDom,
+        states: &'a ElementStates,
+        id: ElementId,
+    ) -> Option<Self> {
+        let vnode = if is_document_node(id) {
+            Some(vdom.get_element(id)?)
+        } else {
+            None
+        };
+        match &rdom[id].node_type {
+            NodeType::Element { tag, .. } => Some(ElementInfo {
+                id,
+                tag,
+                vnode,
+                states,
+            }),
+            _ => None,
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        match self.vnode {
+            Some(VNode::Element(el)) => el
+                .attributes
+                .iter()
+                .find(|a| a.name == name)
+                .and_then(|a| a.value.as_text()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Rule {
+    selectors: Vec<Selector>,
+    declarations: Vec<Declaration>,
+}
+
+/// A selector in a stylesheet, as the index of its rule and its index in the rule.
+type SelectorRef = (usize, usize);
+
+/// How the selectors of a stylesheet use a pseudo-class.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+struct PseudoClassUse {
+    /// If the pseudo-class is on the subject of a selector, so a change of the state can
+    /// restyle the element itself.
+    subject: bool,
+    /// If the pseudo-class is on an ancestor in a selector, so a change of the state can
+    /// restyle the element's descendants.
+    ancestor: bool,
+}
+
+/// The selectors of a stylesheet by the most selective part of their subject, so an element is
+/// only matched against the selectors that could apply to it.
+#[derive(Clone, PartialEq, Debug, Default)]
+struct SelectorIndex {
+    by_id: FxHashMap<String, Vec<SelectorRef>>,
+    by_class: FxHashMap<String, Vec<SelectorRef>>,
+    by_tag: FxHashMap<String, Vec<SelectorRef>>,
+    /// The selectors whose subject has no id, class or tag.
+    universal: Vec<SelectorRef>,
+    /// How each [`PseudoClass`] is used, by its discriminant.
+    pseudo_classes: [PseudoClassUse; 4],
+}
+
+impl SelectorIndex {
+    fn new(rules: &[Rule]) -> Self {
+        let mut index = Self::default();
+        for (r, rule) in rules.iter().enumerate() {
+            for (s, selector) in rule.selectors.iter().enumerate() {
+                let subject = &selector.subject;
+                let bucket = if let Some(id) = &subject.id {
+                    index.by_id.entry(id.clone()).or_default()
+                } else if let Some(class) = subject.classes.first() {
+                    index.by_class.entry(class.clone()).or_default()
+                } else if let Some(tag) = &subject.tag {
+                    index.by_tag.entry(tag.clone()).or_default()
+                } else {
+                    &mut index.universal
+                };
+                bucket.push((r, s));
+
+                for pseudo_class in &subject.pseudo_classes {
+                    index.pseudo_classes[*pseudo_class as usize].subject = true;
+                }
+                for (_, compound) in &selector.ancestors {
+                    for pseudo_class in &compound.pseudo_classes {
+                        index.pseudo_classes[*pseudo_class as usize].ancestor = true;
+                    }
+                }
+            }
+        }
+        index
+    }
+
+    /// The selectors that could match an element, in source order.
+    fn candidates(&self, element: &ElementInfo) -> Vec<SelectorRef> {
+        let mut candidates = self.universal.clone();
+        if let Some(selectors) = self.by_tag.get(element.tag) {
+            candidates.extend(selectors);
+        }
+        if let Some(selectors) = element.attribute("id").and_then(|id| self.by_id.get(id)) {
+            candidates.extend(selectors);
+        }
+        for class in element
+            .attribute("class")
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            if let Some(selectors) = self.by_class.get(class) {
+                candidates.extend(selectors);
+            }
+        }
+        candidates.sort_unstable();
+        // an element can list the same class twice
+        candidates.dedup();
+        candidates
+    }
+}
+
+/// A parsed stylesheet.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct StyleSheet {
+    rules: Vec<Rule>,
+    index: SelectorIndex,
+}
+
+impl StyleSheet {
+    /// Parse a stylesheet, skipping any rules or declarations that are invalid.
+    pub(crate) fn parse(css: &str) -> Self {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let rules: Vec<_> = RuleListParser::new_for_stylesheet(&mut parser, RuleParser)
+            .filter_map(Result::ok)
+            .collect();
+        let index = SelectorIndex::new(&rules);
+        Self { rules, index }
+    }
+
+    /// The rules that match an element in source order, each with the specificity of its most
+    /// specific matching selector.
+    fn matching_rules(
+        &self,
+        element: &ElementInfo,
+        rdom: &Dom,
+        vdom: &VirtualDom,
+    ) -> Vec<(&Rule, u32)> {
+        let mut matched: Vec<(usize, u32)> = Vec::new();
+        for (r, s) in self.index.candidates(element) {
+            let selector = &self.rules[r].selectors[s];
+            if !selector.matches(element, rdom, vdom) {
+                continue;
+            }
+            let specificity = selector.specificity();
+            match matched.last_mut() {
+                Some((last, max)) if *last == r => *max = (*max).max(specificity),
+                _ => matched.push((r, specificity)),
+            }
+        }
+        matched
+            .into_iter()
+            .map(|(r, specificity)| (&self.rules[r], specificity))
+            .collect()
+    }
+}
+
+/// Parse a declaration block like the one in an inline `style` attribute, skipping any
+/// declarations that are invalid.
+pub(crate) fn parse_declarations(css: &str) -> Vec<Declaration> {
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    DeclarationListParser::new(&mut parser, DeclarationBlockParser)
+        .filter_map(Result::ok)
+        .collect()
+}
+
+struct RuleParser;
+
+impl<'i> QualifiedRuleParser<'i> for RuleParser {
+    type Prelude = Vec<Selector>;
+    type QualifiedRule = Rule;
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::Prelude, ParseError<'i, Self::Error>> {
+        parse_selector_list(input)
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: Self::Prelude,
+        _start: &ParserState,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::QualifiedRule, ParseError<'i, Self::Error>> {
+        let declarations = DeclarationListParser::new(input, DeclarationBlockParser)
+            .filter_map(Result::ok)
+            .collect();
+        Ok(Rule {
+            selectors: prelude,
+            declarations,
+        })
+    }
+}
+
+impl<'i> AtRuleParser<'i> for RuleParser {
+    type Prelude = ();
+    type AtRule = Rule;
+    type Error = ();
+}
+
+struct DeclarationBlockParser;
+
+impl<'i> DeclarationParser<'i> for DeclarationBlockParser {
+    type Declaration = Declaration;
+    type Error = ();
+
+    fn parse_value<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::Declaration, ParseError<'i, Self::Error>> {
+        let start = input.position();
+        while input.next().is_ok() {}
+        let raw = input.slice_from(start).trim();
+        let (value, important) = match raw.rfind('!') {
+            Some(i) if raw[i + 1..].trim().eq_ignore_ascii_case("important") => {
+                (raw[..i].trim(), true)
+            }
+            _ => (raw, false),
+        };
+
+        // drop declarations that parcel_css doesn't understand
+        let mut value_input = ParserInput::new(value);
+        let mut value_parser = Parser::new(&mut value_input);
+        Property::parse(
+            name.as_ref().into(),
+            &mut value_parser,
+            &ParserOptions::default(),
+        )
+        .map_err(|_| input.new_custom_error(()))?;
+
+        Ok(Declaration {
+            name: name.to_ascii_lowercase(),
+            value: value.to_string(),
+            important,
+        })
+    }
+}
+
+impl<'i> AtRuleParser<'i> for DeclarationBlockParser {
+    type Prelude = ();
+    type AtRule = Declaration;
+    type Error = ();
+}
+
+fn parse_selector_list<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Vec<Selector>, ParseError<'i, ()>> {
+    let mut selectors = Vec::new();
+    let mut builder = SelectorBuilder::default();
+    loop {
+        let token = match input.next_including_whitespace() {
+            Ok(token) => token.clone(),
+            Err(_) => break,
+        };
+        match token {
+            Token::WhiteSpace(_) => {
+                if builder.started && builder.pending.is_none() {
+                    builder.pending = Some(Combinator::Descendant);
+                }
+            }
+            Token::Delim('>') => {
+                if !builder.started {
+                    return Err(input.new_custom_error(()));
+                }
+                builder.pending = Some(Combinator::Child);
+            }
+            Token::Comma => selectors.push(builder.finish(input)?),
+            Token::Ident(tag) => {
+                builder.start_compound();
+                if builder.started {
+                    // the type selector must come first in a compound selector
+                    return Err(input.new_custom_error(()));
+                }
+                builder.current.tag = Some(tag.to_ascii_lowercase());
+                builder.started = true;
+            }
+            Token::Delim('*') => {
+                builder.start_compound();
+                builder.started = true;
+            }
+            Token::IDHash(id) => {
+                builder.start_compound();
+                builder.current.id = Some(id.to_string());
+                builder.started = true;
+            }
+            Token::Delim('.') => {
+                builder.start_compound();
+                let class = input.expect_ident_cloned()?;
+                builder.current.classes.push(class.to_string());
+                builder.started = true;
+            }
+            Token::SquareBracketBlock => {
+                builder.start_compound();
+                let attribute = input.parse_nested_block(parse_attribute_selector)?;
+                builder.current.attributes.push(attribute);
+                builder.started = true;
+            }
+            Token::Colon => {
+                builder.start_compound();
+                let name = input.expect_ident_cloned()?;
+                if name.eq_ignore_ascii_case("root") {
+                    builder.current.root = true;
+                    builder.started = true;
+                    continue;
+                }
+                // drop the whole rule for pseudo-classes we can't match, like browsers do
+                let pseudo_class =
+                    PseudoClass::parse(&name).ok_or_else(|| input.new_custom_error(()))?;
+                builder.current.pseudo_classes.push(pseudo_class);
+                builder.started = true;
+            }
+            _ => return Err(input.new_custom_error(())),
+        }
+    }
+    selectors.push(builder.finish(input)?);
+    Ok(selectors)
+}
+
+#[derive(Default)]
+struct SelectorBuilder {
+    compounds: Vec<CompoundSelector>,
+    combinators: Vec<Combinator>,
+    current: CompoundSelector,
+    /// If any part of the current compound selector has been parsed.
+    started: bool,
+    /// The combinator between the current compound selector and the next one.
+    pending: Option<Combinator>,
+}
+
+impl SelectorBuilder {
+    /// Move on to a new compound selector if a combinator was parsed since the last one.
+    fn start_compound(&mut self) {
+        if let Some(combinator) = self.pending.take() {
+            self.compounds.push(std::mem::take(&mut self.current));
+            self.combinators.push(combinator);
+            self.started = false;
+        }
+    }
+
+    fn finish<'i>(&mut self, input: &Parser<'i, '_>) -> Result<Selector, ParseError<'i, ()>> {
+        let builder = std::mem::take(self);
+        if !builder.started || builder.pending == Some(Combinator::Child) {
+            return Err(input.new_custom_error(()));
+        }
+        let ancestors = builder
+            .combinators
+            .into_iter()
+            .zip(builder.compounds)
+            .rev()
+            .collect();
+        Ok(Selector {
+            subject: builder.current,
+            ancestors,
+        })
+    }
+}
+
+fn parse_attribute_selector<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<(String, AttributeOperator), ParseError<'i, ()>> {
+    let name = input.expect_ident_cloned()?.to_string();
+    if input.is_exhausted() {
+        return Ok((name, AttributeOperator::Exists));
+    }
+    let operator: fn(String) -> AttributeOperator = match input.next()?.clone() {
+        Token::Delim('=') => AttributeOperator::Equals,
+        Token::IncludeMatch => AttributeOperator::Includes,
+        Token::DashMatch => AttributeOperator::DashMatch,
+        Token::PrefixMatch => AttributeOperator::Prefix,
+        Token::SuffixMatch => AttributeOperator::Suffix,
+        Token::SubstringMatch => AttributeOperator::Substring,
+        _ => return Err(input.new_custom_error(())),
+    };
+    let value = input.expect_ident_or_string()?.to_string();
+    input.expect_exhausted()?;
+    Ok((name, operator(value)))
+}
+
+/// Where a stylesheet comes from. Later origins take precedence over earlier ones, except for
+/// `!important` declarations, where the order is reversed.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Origin {
+    UserAgent,
+    Author,
+}
+
+#[derive(Default)]
+struct CascadeInner {
+    /// The default styles for html elements. Every author style wins over these.
+    user_agent_sheet: StyleSheet,
+    /// Stylesheets that apply to every document, e.g. from the [`crate::Config`].
+    user_sheets: Vec<StyleSheet>,
+    /// The text of all `style` elements and the stylesheet parsed from it.
+    style_elements: (String, StyleSheet),
+    /// The `style` elements the text was collected from.
+    style_ids: Vec<ElementId>,
+    /// The declarations that apply to each element with their origin, from lowest to highest
+    /// specificity.
+    matched: FxHashMap<ElementId, Vec<(Origin, Declaration)>>,
+    /// The element states the declarations were matched with.
+    states: ElementStates,
+    /// If every element has been matched at least once.
+    initialized: bool,
+}
+
+impl CascadeInner {
+    fn sheets(&self) -> impl Iterator<Item = (Origin, &StyleSheet)> {
+        let author_sheets = self
+            .user_sheets
+            .iter()
+            .chain(std::iter::once(&self.style_elements.1));
+        std::iter::once((Origin::UserAgent, &self.user_agent_sheet))
+            .chain(author_sheets.map(|s| (Origin::Author, s)))
+    }
+
+    /// How any of the stylesheets uses a pseudo-class.
+    fn pseudo_class_use(&self, pseudo_class: PseudoClass) -> PseudoClassUse {
+        self.sheets()
+            .map(|(_, sheet)| sheet.index.pseudo_classes[pseudo_class as usize])
+            .fold(PseudoClassUse::default(), |a, b| PseudoClassUse {
+                subject: a.subject || b.subject,
+                ancestor: a.ancestor || b.ancestor,
+            })
+    }
+
+    /// If a `style` element or the text in one may have changed.
+    fn style_elements_changed(&self, rdom: &Dom, dirty: &[(ElementId, NodeMask)]) -> bool {
+        self.style_ids.iter().any(|id| !is_style_element(rdom, *id))
+            || dirty.iter().any(|(id, _)| {
+                is_style_element(rdom, *id)
+                    || rdom
+                        .get(id.0)
+                        .and_then(|n| n.parent)
+                        .map_or(false, |parent| is_style_element(rdom, parent))
+            })
+    }
+
+    /// Collect the text of the style elements in document order and parse it again if it
+    /// changed. Returns if the stylesheet changed.
+    fn update_style_elements(&mut self, rdom: &Dom) -> bool {
+        let mut style_text = String::new();
+        let mut style_ids = Vec::new();
+        rdom.traverse_depth_first(|n| {
+            if let NodeType::Element { tag, children, .. } = &n.node_type {
+                if tag == "style" {
+                    style_ids.push(n.id);
+                    for child in children {
+                        if let NodeType::Text { text } = &rdom[*child].node_type {
+                            style_text += text;
+                            style_text.push('\n');
+                        }
+                    }
+                }
+            }
+        });
+        self.style_ids = style_ids;
+        if self.style_elements.0 == style_text {
+            return false;
+        }
+        let sheet = StyleSheet::parse(&style_text);
+        self.style_elements = (style_text, sheet);
+        true
+    }
+
+    /// The declarations that apply to an element, from lowest to highest precedence.
+    fn match_element(
+        &self,
+        element: &ElementInfo,
+        rdom: &Dom,
+        vdom: &VirtualDom,
+    ) -> Vec<(Origin, Declaration)> {
+        let mut declarations = Vec::new();
+        for (origin, sheet) in self.sheets() {
+            for (rule, specificity) in sheet.matching_rules(element, rdom, vdom) {
+                declarations.extend(rule.declarations.iter().map(|d| ((origin, specificity), d)));
+            }
+        }
+        // later rules win over earlier ones with the same origin and specificity
+        declarations.sort_by_key(|(precedence, _)| *precedence);
+        declarations
+            .into_iter()
+            .map(|((origin, _), d)| (origin, d.clone()))
+            .collect()
+    }
+}
+
+fn is_style_element(rdom: &Dom, id: ElementId) -> bool {
+    matches!(
+        rdom.get(id.0).map(|n| &n.node_type),
+        Some(NodeType::Element { tag, .. }) if tag == "style"
+    )
+}
+
+/// The elements whose state changed, with the pseudo-class that may match them differently.
+fn changed_states(old: &ElementStates, new: &ElementStates) -> Vec<(PseudoClass, ElementId)> {
+    fn difference<'a>(
+        a: &'a [ElementId],
+        b: &'a [ElementId],
+    ) -> impl Iterator<Item = ElementId> + 'a {
+        let removed = a.iter().filter(|id| !b.contains(id));
+        let added = b.iter().filter(|id| !a.contains(id));
+        removed.chain(added).copied()
+    }
+    let mut changed: Vec<_> = difference(&old.hovered, &new.hovered)
+        .map(|id| (PseudoClass::Hover, id))
+        .chain(difference(&old.active, &new.active).map(|id| (PseudoClass::Active, id)))
+        .collect();
+    if old.focused != new.focused {
+        for id in old.focused.into_iter().chain(new.focused) {
+            changed.push((PseudoClass::Focus, id));
+            changed.push((PseudoClass::FocusVisible, id));
+        }
+    } else if old.focus_visible != new.focus_visible {
+        if let Some(id) = new.focused {
+            changed.push((PseudoClass::FocusVisible, id));
+        }
+    }
+    changed
+}
+
+/// Matches the rules from every stylesheet against the elements in the dom.
+#[derive(Clone, Default)]
+pub(crate) struct Cascade {
+    inner: Rc<RefCell<CascadeInner>>,
+}
+
+impl Cascade {
+    pub(crate) fn new(user_agent_sheet: Option<&str>, stylesheets: &[String]) -> Self {
+        let inner = CascadeInner {
+            user_agent_sheet: user_agent_sheet.map(StyleSheet::parse).unwrap_or_default(),
+            user_sheets: stylesheets.iter().map(|s| StyleSheet::parse(s)).collect(),
+            ..Default::default()
+        };
+        Self {
+            inner: Rc::new(RefCell::new(inner)),
+        }
+    }
+
+    /// The declarations that apply to an element with their origin, from lowest to highest
+    /// precedence among the normal declarations.
+    pub(crate) fn declarations(&self, id: ElementId) -> Vec<(Origin, Declaration)> {
+        self.inner
+            .borrow()
+            .matched
+            .get(&id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Match the elements that may be styled differently against the stylesheets again and
+    /// return the ones whose declarations changed.
+    pub(crate) fn update(
+        &self,
+        rdom: &Dom,
+        vdom: &VirtualDom,
+        dirty: &[(ElementId, NodeMask)],
+        states: &ElementStates,
+    ) -> Vec<(ElementId, NodeMask)> {
+        let mut inner = self.inner.borrow_mut();
+
+        let mut everything = !inner.initialized;
+        if everything || inner.style_elements_changed(rdom, dirty) {
+            everything |= inner.update_style_elements(rdom);
+        }
+
+        // the subtrees and the single elements that need to be matched again
+        let mut subtrees = Vec::new();
+        let mut elements = Vec::new();
+        if everything {
+            subtrees.push(ElementId(rdom.root_id()));
+        } else {
+            let structure = NodeMask::new_with_attrs(AttributeMask::All).with_tag();
+            subtrees.extend(
+                dirty
+                    .iter()
+                    .filter(|(_, mask)| mask.overlaps(&structure))
+                    .map(|(id, _)| *id),
+            );
+            for (pseudo_class, id) in changed_states(&inner.states, states) {
+                let used = inner.pseudo_class_use(pseudo_class);
+                if used.ancestor {
+                    subtrees.push(id);
+                } else if used.subject {
+                    elements.push(id);
+                }
+            }
+        }
+        inner.states = states.clone();
+        inner.initialized = true;
+
+        let mut targets = FxHashSet::default();
+        let mut stack: Vec<_> = subtrees
+            .into_iter()
+            .filter(|id| rdom.get(id.0).is_some())
+            .collect();
+        while let Some(id) = stack.pop() {
+            // the subtree may already have been added from an ancestor
+            if !targets.insert(id) {
+                continue;
+            }
+            if let NodeType::Element { children, .. } = &rdom[id].node_type {
+                stack.extend(children);
+            }
+        }
+        targets.extend(elements.into_iter().filter(|id| rdom.get(id.0).is_some()));
+        if everything {
+            // forget the elements that were removed
+            inner.matched.retain(|id, _| targets.contains(id));
+        }
+
+        let mut changed = Vec::new();
+        for id in targets {
+            let declarations = match ElementInfo::new(rdom, vdom, states, id) {
+                Some(element) => inner.match_element(&element, rdom, vdom),
+                None => Vec::new(),
+            };
+            let old = inner
+                .matched
+                .get(&id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            if old != declarations.as_slice() {
+                changed.push((id, NodeMask::ALL));
+            }
+            if declarations.is_empty() {
+                inner.matched.remove(&id);
+            } else {
+                inner.matched.insert(id, declarations);
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dioxus::prelude::*;
+    use dioxus_native_core::real_dom::RealDom;
+
+    use super::*;
+    use crate::style::by_precedence;
+
+    fn tag(name: &str) -> CompoundSelector {
+        CompoundSelector {
+            tag: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn selectors(css: &str) -> Vec<Selector> {
+        let sheet = StyleSheet::parse(css);
+        assert_eq!(sheet.rules.len(), 1, "{}", css);
+        sheet.rules[0].selectors.clone()
+    }
+
+    #[test]
+    fn parses_combinators() {
+        assert_eq!(
+            selectors("a > b {}"),
+            [Selector {
+                subject: tag("b"),
+                ancestors: vec![(Combinator::Child, tag("a"))],
+            }]
+        );
+        assert_eq!(
+            selectors("a b {}"),
+            [Selector {
+                subject: tag("b"),
+                ancestors: vec![(Combinator::Descendant, tag("a"))],
+            }]
+        );
+        let subject = |name| Selector {
+            subject: tag(name),
+            ancestors: Vec::new(),
+        };
+        assert_eq!(selectors("a , b {}"), [subject("a"), subject("b")]);
+        // the ancestors go from right to left
+        assert_eq!(
+            selectors("ul > li a {}")[0].ancestors,
+            [
+                (Combinator::Descendant, tag("li")),
+                (Combinator::Child, tag("ul")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_attribute_operators() {
+        let css = r#"[a][b=x][c~="y"][d|=z][e^=w][f$=v][g*=u] {}"#;
+        assert_eq!(
+            selectors(css)[0].subject.attributes,
+            [
+                ("a".to_string(), AttributeOperator::Exists),
+                ("b".to_string(), AttributeOperator::Equals("x".to_string())),
+                (
+                    "c".to_string(),
+                    AttributeOperator::Includes("y".to_string())
+                ),
+                (
+                    "d".to_string(),
+                    AttributeOperator::DashMatch("z".to_string())
+                ),
+                ("e".to_string(), AttributeOperator::Prefix("w".to_string())),
+                ("f".to_string(), AttributeOperator::Suffix("v".to_string())),
+                (
+                    "g".to_string(),
+                    AttributeOperator::Substring("u".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_attribute_values() {
+        let op = |f: fn(String) -> AttributeOperator, v: &str| f(v.to_string());
+        assert!(op(AttributeOperator::Includes, "b").matches("a b c"));
+        assert!(!op(AttributeOperator::Includes, "b").matches("abc"));
+        assert!(op(AttributeOperator::DashMatch, "en").matches("en"));
+        assert!(op(AttributeOperator::DashMatch, "en").matches("en-US"));
+        assert!(!op(AttributeOperator::DashMatch, "en").matches("english"));
+        assert!(op(AttributeOperator::Prefix, "ab").matches("abc"));
+        assert!(op(AttributeOperator::Suffix, "bc").matches("abc"));
+        assert!(op(AttributeOperator::Substring, "b").matches("abc"));
+        assert!(!op(AttributeOperator::Substring, "").matches("abc"));
+    }
+
+    #[test]
+    fn orders_by_specificity() {
+        let specificity = |css: &str| selectors(&format!("{} {{}}", css))[0].specificity();
+        let ordered = ["*", "div", "div p", ".a", "div.a", ".a.b", "#a", "#a .b"];
+        for pair in ordered.windows(2) {
+            assert!(
+                specificity(pair[0]) < specificity(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+        // no number of type selectors adds up to a class
+        assert!(specificity("a b c d e f") < specificity(".a"));
+    }
+
+    #[test]
+    fn skips_invalid_rules() {
+        let sheet = StyleSheet::parse(
+            "a:unknown { color: red } p > { color: red } b { color: blue; width: nonsense }",
+        );
+        assert_eq!(sheet.rules.len(), 1);
+        assert_eq!(sheet.rules[0].selectors[0].subject, tag("b"));
+        assert_eq!(
+            sheet.rules[0].declarations,
+            [Declaration {
+                name: "color".to_string(),
+                value: "blue".to_string(),
+                important: false,
+            }]
+        );
+    }
+
+    /// Build the dom for an app and match it against the stylesheets.
+    fn cascade(app: Component<()>, user_agent: &str, author: &str) -> (Dom, Cascade) {
+        let mut vdom = VirtualDom::new(app);
+        let mut rdom: Dom = RealDom::new();
+        let dirty = rdom.apply_mutations(vec![vdom.rebuild()]);
+        let cascade = Cascade::new(Some(user_agent), &[author.to_string()]);
+        cascade.update(&rdom, &vdom, &dirty, &ElementStates::default());
+        (rdom, cascade)
+    }
+
+    fn find(rdom: &Dom, name: &str) -> ElementId {
+        let mut found = None;
+        rdom.traverse_depth_first(|n| {
+            if let NodeType::Element { tag, .. } = &n.node_type {
+                if tag == name && found.is_none() {
+                    found = Some(n.id);
+                }
+            }
+        });
+        found.unwrap()
+    }
+
+    /// The value of a property after cascading the declarations of an element.
+    fn value(cascade: &Cascade, id: ElementId, name: &str) -> Option<String> {
+        by_precedence(cascade.declarations(id), Vec::new(), Vec::new())
+            .into_iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value)
+    }
+
+    #[test]
+    fn matches_combinators_against_the_dom() {
+        fn app(cx: Scope) -> Element {
+            cx.render(rsx! {
+                ul { li { a {} } }
+            })
+        }
+        let (rdom, cascade) = cascade(
+            app,
+            "",
+            "li > a { color: red } ul > a { width: 1px } ul a { height: 1px }",
+        );
+        let a = find(&rdom, "a");
+        assert_eq!(value(&cascade, a, "color").as_deref(), Some("red"));
+        assert_eq!(value(&cascade, a, "width"), None);
+        assert_eq!(value(&cascade, a, "height").as_deref(), Some("1px"));
+    }
+
+    #[test]
+    fn matches_the_root_only_with_root_selectors() {
+        fn app(cx: Scope) -> Element {
+            cx.render(rsx! { div {} })
+        }
+        let (rdom, cascade) = cascade(app, ":root { color: red } * { width: 1px }", "");
+        let root = ElementId(rdom.root_id());
+        assert_eq!(value(&cascade, root, "color").as_deref(), Some("red"));
+        assert_eq!(value(&cascade, root, "width"), None);
+        let div = find(&rdom, "div");
+        assert_eq!(value(&cascade, div, "color"), None);
+        assert_eq!(value(&cascade, div, "width").as_deref(), Some("1px"));
+    }
+
+    #[test]
+    fn orders_origins_and_importance() {
+        fn app(cx: Scope) -> Element {
+            cx.render(rsx! {
+                div { p {} }
+            })
+        }
+        let (rdom, cascade) = cascade(
+            app,
+            "div p { color: red; width: 1px !important }",
+            "p { color: blue; width: 2px !important }",
+        );
+        let p = find(&rdom, "p");
+        // author styles win over more specific default styles
+        assert_eq!(value(&cascade, p, "color").as_deref(), Some("blue"));
+        // but not over !important default styles
+        assert_eq!(value(&cascade, p, "width").as_deref(), Some("1px"));
+    }
+}
@@ -0,0 +1,318 @@
+use parcel_css::properties::font::GenericFontFamily;
+use piet_common::{Device, ImageFormat, PietText};
+use piet_wgpu::kurbo::Rect;
+use piet_wgpu::{
+    Color, FontFamily, FontStyle, FontWeight, RenderContext, Text, TextLayout, TextLayoutBuilder,
+};
+use taffy::prelude::Size;
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+use crate::style::{Font, FontFamilyName};
+
+/// The font size used for text when no other size is specified.
+pub(crate) const DEFAULT_FONT_SIZE: f64 = 16.0;
+
+/// Build the layout for a text node. Both measuring and rasterizing go through this.
+pub(crate) fn build_text_layout<T: Text>(
+    factory: &mut T,
+    text: &str,
+    font: &Font,
+    color: Color,
+) -> T::TextLayout {
+    let family = resolve_family(factory, font);
+    factory
+        .new_text_layout(text.to_string())
+        .font(family, font.size)
+        .default_attribute(FontWeight::new(font.weight))
+        .default_attribute(if font.italic {
+            FontStyle::Italic
+        } else {
+            FontStyle::Regular
+        })
+        .text_color(color)
+        .build()
+        .unwrap()
+}
+
+/// Find the first family in the font's family list that is available.
+fn resolve_family<T: Text>(factory: &mut T, font: &Font) -> FontFamily {
+    for family in &font.family {
+        match family {
+            FontFamilyName::Generic(generic) => {
+                return match generic {
+                    GenericFontFamily::Serif => FontFamily::SERIF,
+                    GenericFontFamily::SansSerif => FontFamily::SANS_SERIF,
+                    GenericFontFamily::Monospace => FontFamily::MONOSPACE,
+                    _ => FontFamily::SYSTEM_UI,
+                }
+            }
+            FontFamilyName::Named(name) => {
+                if let Some(family) = factory.font_family(name) {
+                    return family;
+                }
+            }
+        }
+    }
+    FontFamily::SYSTEM_UI
+}
+
+/// A line of shaped text.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct Line {
+    pub text: String,
+    /// The width of the glyphs, without trailing whitespace.
+    pub width: f64,
+    /// The natural height of the line, before `line-height` is applied.
+    pub height: f64,
+}
+
+/// A line of text painted into a bitmap of premultiplied RGBA pixels, row by row. Lines that
+/// don't paint anything have an empty bitmap.
+pub(crate) struct TextBitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+    /// Where the bitmap goes relative to the top left corner of the text. The bitmap is larger
+    /// than the text to fit glyphs that reach outside of their line and the blur.
+    pub bounds: Rect,
+}
+
+/// Measures and rasterizes text with the same text stack, independent of the render context the
+/// text ends up in.
+pub(crate) struct TextContext {
+    device: Device,
+    factory: PietText,
+}
+
+impl TextContext {
+    pub(crate) fn new() -> Result<Self, piet_common::Error> {
+        let mut device = Device::new()?;
+        let mut target = device.bitmap_target(1, 1, 1.0)?;
+        let mut piet = target.render_context();
+        let factory = piet.text().clone();
+        piet.finish()?;
+        drop(piet);
+        drop(target);
+        Ok(Self { device, factory })
+    }
+
+    /// Paint a line of text into a bitmap, blurred like a shadow with the given blur radius.
+    ///
+    /// The bitmap is empty if the line doesn't paint anything.
+    pub(crate) fn rasterize(
+        &mut self,
+        text: &str,
+        font: &Font,
+        color: Color,
+        blur: f64,
+    ) -> TextBitmap {
+        let layout = build_text_layout(&mut self.factory, text, font, color.clone());
+        let size = layout.size();
+        // a css blur radius is twice the standard deviation, and a gaussian fades out after
+        // three standard deviations
+        let margin = (blur * 1.5).ceil();
+        let bounds = layout
+            .image_bounds()
+            .union(size.to_rect())
+            .inflate(margin, margin)
+            .expand();
+        let mut bitmap = TextBitmap {
+            width: bounds.width() as usize,
+            height: bounds.height() as usize,
+            pixels: Vec::new(),
+            bounds,
+        };
+        if text.trim().is_empty() || bitmap.width == 0 || bitmap.height == 0 {
+            bitmap.width = 0;
+            bitmap.height = 0;
+            return bitmap;
+        }
+        match self.paint(text, font, color, &bitmap) {
+            Ok(pixels) => bitmap.pixels = pixels,
+            Err(_) => {
+                bitmap.width = 0;
+                bitmap.height = 0;
+                return bitmap;
+            }
+        }
+        if blur > 0.0 {
+            blur_pixels(&mut bitmap.pixels, bitmap.width, bitmap.height, blur / 2.0);
+        }
+        bitmap
+    }
+
+    /// Paint the text into the area of a bitmap and read back its pixels.
+    fn paint(
+        &mut self,
+        text: &str,
+        font: &Font,
+        color: Color,
+        bitmap: &TextBitmap,
+    ) -> Result<Vec<u8>, piet_common::Error> {
+        let mut target = self
+            .device
+            .bitmap_target(bitmap.width, bitmap.height, 1.0)?;
+        let mut piet = target.render_context();
+        let layout = build_text_layout(piet.text(), text, font, color);
+        piet.draw_text(&layout, (-bitmap.bounds.x0, -bitmap.bounds.y0));
+        piet.finish()?;
+        drop(piet);
+        let mut pixels = vec![0; bitmap.width * bitmap.height * 4];
+        target.copy_raw_pixels(ImageFormat::RgbaPremul, &mut pixels)?;
+        Ok(pixels)
+    }
+
+    /// Break the text into lines that fit in the given width. Words wider than that overflow.
+    pub(crate) fn wrap(&mut self, text: &str, font: &Font, max_width: f64) -> Vec<Line> {
+        // shape the text once and measure each candidate line by where its ends are in the layout
+        let layout = build_text_layout(&mut self.factory, text, font, Color::BLACK);
+        let x = |idx: usize| layout.hit_test_text_position(idx).point.x;
+        let line = |start: usize, end: usize| {
+            let trimmed = text[start..end].trim_end();
+            let number = layout.hit_test_text_position(start).line;
+            Line {
+                text: trimmed.to_string(),
+                width: x(start + trimmed.len()) - x(start),
+                height: layout.line_metric(number).map_or(0.0, |m| m.height),
+            }
+        };
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        let mut last_break = None;
+        for (idx, opportunity) in linebreaks(text) {
+            if let Some(end) = last_break {
+                let line_end = line_start + text[line_start..idx].trim_end().len();
+                if x(line_end) - x(line_start) > max_width {
+                    lines.push(line(line_start, end));
+                    line_start = end;
+                }
+            }
+            if let BreakOpportunity::Mandatory = opportunity {
+                lines.push(line(line_start, idx));
+                line_start = idx;
+                last_break = None;
+            } else {
+                last_break = Some(idx);
+            }
+        }
+        lines
+    }
+
+    /// Get the size of the text after shaping and wrapping it to the given width.
+    pub(crate) fn measure(&mut self, text: &str, font: &Font, max_width: f64) -> Size<f32> {
+        let mut width: f64 = 0.0;
+        let mut height = 0.0;
+        for line in self.wrap(text, font, max_width) {
+            width = width.max(line.width);
+            height += font.line_box_height(line.height);
+        }
+        // round up so wrapping again at the computed width produces the same lines
+        Size {
+            width: width.ceil() as f32,
+            height: height.ceil() as f32,
+        }
+    }
+
+    /// Shape a single line of text without wrapping it.
+    pub(crate) fn line(&mut self, text: &str, font: &Font) -> Line {
+        let size = build_text_layout(&mut self.factory, text, font, Color::BLACK).size();
+        Line {
+            text: text.to_string(),
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+/// Approximate a gaussian blur with a standard deviation of `sigma` by box blurring premultiplied
+/// RGBA pixels three times in each direction.
+fn blur_pixels(pixels: &mut [u8], width: usize, height: usize, sigma: f64) {
+    // three box blurs of this radius add up to about the variance of the gaussian
+    let radius = (((4.0 * sigma * sigma + 1.0).sqrt() - 1.0) / 2.0).round() as usize;
+    if radius == 0 {
+        return;
+    }
+    let mut line = Vec::new();
+    for _ in 0..3 {
+        for y in 0..height {
+            box_blur(pixels, y * width * 4, 4, width, radius, &mut line);
+        }
+        for x in 0..width {
+            box_blur(pixels, x * 4, width * 4, height, radius, &mut line);
+        }
+    }
+}
+
+/// Replace each of the `len` pixels that start at `start` and are `stride` bytes apart with the
+/// average of the pixels within `radius` of it. Pixels past either end count as transparent.
+fn box_blur(
+    pixels: &mut [u8],
+    start: usize,
+    stride: usize,
+    len: usize,
+    radius: usize,
+    line: &mut Vec<[u8; 4]>,
+) {
+    line.clear();
+    line.extend((0..len).map(|i| {
+        let p = start + i * stride;
+        [pixels[p], pixels[p + 1], pixels[p + 2], pixels[p + 3]]
+    }));
+    let window = 2 * radius as u32 + 1;
+    let mut sum = [0u32; 4];
+    for pixel in line.iter().take(radius) {
+        sum.iter_mut().zip(pixel).for_each(|(s, c)| *s += *c as u32);
+    }
+    for i in 0..len {
+        if let Some(pixel) = line.get(i + radius) {
+            sum.iter_mut().zip(pixel).for_each(|(s, c)| *s += *c as u32);
+        }
+        let p = start + i * stride;
+        for (out, s) in pixels[p..p + 4].iter_mut().zip(sum) {
+            *out = ((s + window / 2) / window) as u8;
+        }
+        if i >= radius {
+            let pixel = line[i - radius];
+            sum.iter_mut().zip(pixel).for_each(|(s, c)| *s -= c as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: &[Line]) -> Vec<&str> {
+        lines.iter().map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn wrapping_at_the_measured_width_keeps_the_lines() {
+        let mut ctx = TextContext::new().unwrap();
+        let font = Font::default();
+        let text = "the quick brown fox jumps over the lazy dog";
+        let lines = ctx.wrap(text, &font, 100.0);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| l.width <= 100.0));
+
+        let size = ctx.measure(text, &font, 100.0);
+        let rewrapped = ctx.wrap(text, &font, size.width as f64);
+        assert_eq!(texts(&rewrapped), texts(&lines));
+        assert_eq!(ctx.measure(text, &font, size.width as f64), size);
+    }
+
+    #[test]
+    fn breaks_at_newlines() {
+        let mut ctx = TextContext::new().unwrap();
+        let lines = ctx.wrap("one\ntwo three", &Font::default(), 1000.0);
+        assert_eq!(texts(&lines), ["one", "two three"]);
+    }
+
+    #[test]
+    fn overflows_with_words_wider_than_the_line() {
+        let mut ctx = TextContext::new().unwrap();
+        let lines = ctx.wrap("a supercalifragilistic word", &Font::default(), 10.0);
+        assert_eq!(texts(&lines), ["a", "supercalifragilistic", "word"]);
+        assert!(lines[1].width > 10.0);
+    }
+}
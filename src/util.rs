@@ -3,6 +3,10 @@ use parcel_css::values::calc::{Calc, MathFunction};
 use parcel_css::values::color::CssColor;
 use parcel_css::values::length::{Length, LengthValue};
 use parcel_css::values::percentage::DimensionPercentage;
+use parcel_css::values::position::{
+    HorizontalPositionKeyword, Position, PositionComponent, VerticalPositionKeyword,
+};
+use piet_wgpu::kurbo::Vec2;
 use piet_wgpu::Color;
 use taffy::prelude::Size;
 
@@ -17,6 +21,14 @@ pub(crate) enum Axis {
     Max,
 }
 
+/// Translate a color that may be `currentColor`, which is the color of the element's text.
+pub(crate) fn resolve_color(color: &CssColor, current_color: &CssColor) -> Color {
+    match color {
+        CssColor::CurrentColor => translate_color(current_color),
+        color => translate_color(color),
+    }
+}
+
 pub(crate) fn translate_color(color: &CssColor) -> Color {
     let rgb = color.to_rgb();
     if let CssColor::RGBA(rgba) = rgb {
@@ -129,3 +141,39 @@ impl<T: Resolve> Resolve for DimensionPercentage<T> {
         }
     }
 }
+
+/// Resolve a position like `right 10px top` to an offset from the top left corner of an area,
+/// with percentages of `size`.
+pub(crate) fn resolve_position(
+    position: &Position,
+    size: &Size<f32>,
+    viewport_size: &Size<u32>,
+) -> Vec2 {
+    let x = match &position.x {
+        PositionComponent::Center => size.width as f64 / 2.0,
+        PositionComponent::Length(l) => l.resolve(Axis::X, size, viewport_size),
+        PositionComponent::Side(side, offset) => {
+            let offset = offset
+                .as_ref()
+                .map_or(0.0, |o| o.resolve(Axis::X, size, viewport_size));
+            match side {
+                HorizontalPositionKeyword::Left => offset,
+                HorizontalPositionKeyword::Right => size.width as f64 - offset,
+            }
+        }
+    };
+    let y = match &position.y {
+        PositionComponent::Center => size.height as f64 / 2.0,
+        PositionComponent::Length(l) => l.resolve(Axis::Y, size, viewport_size),
+        PositionComponent::Side(side, offset) => {
+            let offset = offset
+                .as_ref()
+                .map_or(0.0, |o| o.resolve(Axis::Y, size, viewport_size));
+            match side {
+                VerticalPositionKeyword::Top => offset,
+                VerticalPositionKeyword::Bottom => size.height as f64 - offset,
+            }
+        }
+    };
+    Vec2::new(x, y)
+}
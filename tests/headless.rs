@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use blitz::{Config, HeadlessApp};
+use dioxus::prelude::*;
+
+const WIDTH: u32 = 40;
+const HEIGHT: u32 = 30;
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+fn headless(root: Component<()>) -> HeadlessApp {
+    let cfg = Config::default().without_default_stylesheet();
+    HeadlessApp::new_cfg(root, WIDTH, HEIGHT, cfg).expect("failed to create the headless app")
+}
+
+/// Render a single frame of an app.
+fn render(root: Component<()>) -> Vec<u8> {
+    headless(root).render_rgba().unwrap()
+}
+
+fn pixel(buffer: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let i = (y * WIDTH + x) as usize * 4;
+    [buffer[i], buffer[i + 1], buffer[i + 2], buffer[i + 3]]
+}
+
+#[test]
+fn paints_backgrounds_and_borders() {
+    fn app(cx: Scope) -> Element {
+        cx.render(rsx! {
+            div {
+                width: "20px",
+                height: "10px",
+                margin_left: "10px",
+                margin_top: "10px",
+                background_color: "rgb(255, 0, 0)",
+                border_width: "2px",
+                border_style: "solid",
+                border_color: "rgb(0, 0, 255)",
+            }
+        })
+    }
+    let buffer = render(app);
+    assert_eq!(buffer.len(), (WIDTH * HEIGHT * 4) as usize);
+    // the window is cleared to white around the element
+    assert_eq!(pixel(&buffer, 0, 0), WHITE);
+    assert_eq!(pixel(&buffer, 5, 15), WHITE);
+    assert_eq!(pixel(&buffer, 35, 25), WHITE);
+    // the width and height are the size of the border box, which starts after the margin
+    assert_eq!(pixel(&buffer, 10, 10), BLUE);
+    assert_eq!(pixel(&buffer, 11, 15), BLUE);
+    assert_eq!(pixel(&buffer, 29, 19), BLUE);
+    assert_eq!(pixel(&buffer, 30, 15), WHITE);
+    assert_eq!(pixel(&buffer, 15, 20), WHITE);
+    // the background fills the inside of the border
+    assert_eq!(pixel(&buffer, 12, 12), RED);
+    assert_eq!(pixel(&buffer, 27, 17), RED);
+}
+
+#[tokio::test]
+async fn keeps_unchanged_pixels_in_partial_redraws() {
+    fn app(cx: Scope) -> Element {
+        let changed = use_state(&cx, || false);
+        use_future(&cx, (), move |_| {
+            let changed = changed.to_owned();
+            async move { changed.set(true) }
+        });
+        let color = if *changed.get() {
+            "rgb(0, 255, 0)"
+        } else {
+            "rgb(255, 0, 0)"
+        };
+        cx.render(rsx! {
+            div {
+                width: "10px",
+                height: "10px",
+                background_color: "rgb(0, 0, 255)",
+            }
+            div {
+                width: "10px",
+                height: "10px",
+                background_color: "{color}",
+            }
+        })
+    }
+    let mut app = headless(app);
+    let before = app.render_rgba().unwrap();
+    tokio::time::timeout(Duration::from_secs(5), app.wait_for_work())
+        .await
+        .expect("the state was never changed");
+    let after = app.render_rgba().unwrap();
+
+    assert_eq!(pixel(&before, 5, 15), RED);
+    assert_eq!(pixel(&after, 5, 15), GREEN);
+    assert_eq!(pixel(&after, 5, 5), BLUE);
+    // only the element that changed is painted again, everything else is kept from the last frame
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            if x < 10 && (10..20).contains(&y) {
+                continue;
+            }
+            assert_eq!(
+                pixel(&after, x, y),
+                pixel(&before, x, y),
+                "pixel at {}, {}",
+                x,
+                y
+            );
+        }
+    }
+}